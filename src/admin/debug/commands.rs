@@ -294,11 +294,11 @@ pub(super) async fn get_room_state(
 		.services
 		.rooms
 		.state_accessor
-		.room_state_full(&room_id)
+		.room_state_pdus(&room_id)
 		.await?
-		.values()
-		.map(PduEvent::to_state_event)
-		.collect();
+		.map(|pdu| pdu.to_state_event())
+		.collect()
+		.await;
 
 	if room_state.is_empty() {
 		return Ok(RoomMessageEventContent::text_plain(
@@ -779,6 +779,29 @@ pub(super) async fn get_verify_keys(
 	Ok(RoomMessageEventContent::notice_markdown(out))
 }
 
+#[admin_command]
+pub(super) async fn recompute_forward_extremities(
+	&self,
+	room_id: OwnedRoomOrAliasId,
+) -> Result<RoomMessageEventContent> {
+	let room_id = self.services.rooms.alias.resolve(&room_id).await?;
+	let state_lock = self.services.rooms.state.mutex.lock(&room_id).await;
+	let leaves = self
+		.services
+		.rooms
+		.state
+		.recompute_forward_extremities(&room_id, &state_lock)
+		.await?;
+	drop(state_lock);
+
+	let mut out = format!("Rebuilt {} forward extremities for {room_id}:\n", leaves.len());
+	for leaf in &leaves {
+		writeln!(out, "- {leaf}")?;
+	}
+
+	Ok(RoomMessageEventContent::notice_markdown(out))
+}
+
 #[admin_command]
 pub(super) async fn resolve_true_destination(
 	&self,
@@ -923,3 +946,15 @@ pub(super) async fn database_stats(
 
 	Ok(RoomMessageEventContent::notice_markdown(out))
 }
+
+#[admin_command]
+pub(super) async fn timeline_gc(&self) -> Result<RoomMessageEventContent> {
+	let timer = Instant::now();
+	let report = self.services.rooms.timeline.gc().await;
+	let elapsed = timer.elapsed();
+
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"Removed {} dangling event ids in {elapsed:?}",
+		report.dangling_event_ids
+	)))
+}
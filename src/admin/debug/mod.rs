@@ -169,6 +169,16 @@ pub(super) enum DebugCommand {
 		server_name: Box<ServerName>,
 	},
 
+	/// - Rebuild a room's forward extremities from its timeline
+	///
+	/// Use this if new events in a room are built on the wrong prev_events,
+	/// e.g. after the stored extremities were lost or corrupted. Every event
+	/// in our timeline which no other event references becomes an extremity.
+	RecomputeForwardExtremities {
+		/// Room ID
+		room_id: OwnedRoomOrAliasId,
+	},
+
 	/// - Runs a server name through conduwuit's true destination resolution
 	///   process
 	///
@@ -207,6 +217,11 @@ pub(super) enum DebugCommand {
 		map: Option<String>,
 	},
 
+	/// - Remove event ids whose PDU no longer exists from the timeline
+	///
+	/// Safe to run while the server is handling requests.
+	TimelineGc,
+
 	/// - Developer test stubs
 	#[command(subcommand)]
 	#[allow(non_snake_case)]
@@ -3,9 +3,11 @@ mod appservice;
 mod globals;
 mod presence;
 mod pusher;
+mod reports;
 mod resolver;
 mod room_alias;
 mod room_state_cache;
+mod room_timeline;
 mod sending;
 mod users;
 
@@ -14,8 +16,9 @@ use conduwuit::Result;
 
 use self::{
 	account_data::AccountDataCommand, appservice::AppserviceCommand, globals::GlobalsCommand,
-	presence::PresenceCommand, pusher::PusherCommand, resolver::ResolverCommand,
-	room_alias::RoomAliasCommand, room_state_cache::RoomStateCacheCommand,
+	presence::PresenceCommand, pusher::PusherCommand, reports::ReportsCommand,
+	resolver::ResolverCommand, room_alias::RoomAliasCommand,
+	room_state_cache::RoomStateCacheCommand, room_timeline::RoomTimelineCommand,
 	sending::SendingCommand, users::UsersCommand,
 };
 use crate::admin_command_dispatch;
@@ -44,6 +47,10 @@ pub(super) enum QueryCommand {
 	#[command(subcommand)]
 	RoomStateCache(RoomStateCacheCommand),
 
+	/// - rooms/timeline iterators and getters
+	#[command(subcommand)]
+	RoomTimeline(RoomTimelineCommand),
+
 	/// - globals.rs iterators and getters
 	#[command(subcommand)]
 	Globals(GlobalsCommand),
@@ -63,4 +70,8 @@ pub(super) enum QueryCommand {
 	/// - pusher service
 	#[command(subcommand)]
 	Pusher(PusherCommand),
+
+	/// - reports service
+	#[command(subcommand)]
+	Reports(ReportsCommand),
 }
@@ -0,0 +1,45 @@
+use clap::Subcommand;
+use conduwuit::Result;
+use futures::StreamExt;
+use ruma::events::room::message::RoomMessageEventContent;
+
+use crate::Command;
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum ReportsCommand {
+	/// - Event reports filed by users, oldest first
+	List {
+		/// Only list reports after this report ID, as returned by a previous
+		/// query
+		#[arg(long)]
+		since: Option<u64>,
+
+		/// Maximum number of reports to list
+		#[arg(short, long, default_value("100"))]
+		limit: usize,
+	},
+}
+
+pub(super) async fn process(
+	subcommand: ReportsCommand,
+	context: &Command<'_>,
+) -> Result<RoomMessageEventContent> {
+	let services = context.services;
+
+	match subcommand {
+		| ReportsCommand::List { since, limit } => {
+			let timer = tokio::time::Instant::now();
+			let results: Vec<_> = services
+				.reports
+				.db
+				.reports_list(limit, since)
+				.collect()
+				.await;
+			let query_time = timer.elapsed();
+
+			Ok(RoomMessageEventContent::notice_markdown(format!(
+				"Query completed in {query_time:?}:\n\n```rs\n{results:#?}\n```"
+			)))
+		},
+	}
+}
@@ -0,0 +1,49 @@
+use clap::Subcommand;
+use conduwuit::{PduCount, Result};
+use futures::{StreamExt, TryStreamExt};
+use ruma::{events::room::message::RoomMessageEventContent, RoomId};
+
+use crate::Command;
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum RoomTimelineCommand {
+	/// - Membership transitions in the room, oldest first
+	MembershipChanges {
+		room_id: Box<RoomId>,
+
+		/// Only list changes after this count, as returned by a previous query
+		#[arg(long)]
+		since: Option<u64>,
+
+		/// Maximum number of changes to list
+		#[arg(short, long, default_value("100"))]
+		limit: usize,
+	},
+}
+
+pub(super) async fn process(
+	subcommand: RoomTimelineCommand,
+	context: &Command<'_>,
+) -> Result<RoomMessageEventContent> {
+	let services = context.services;
+
+	match subcommand {
+		| RoomTimelineCommand::MembershipChanges { room_id, since, limit } => {
+			let timer = tokio::time::Instant::now();
+			let since = since.map_or_else(PduCount::min, PduCount::Normal);
+			let results: Vec<_> = services
+				.rooms
+				.timeline
+				.membership_changes_since(&room_id, since)
+				.await?
+				.take(limit)
+				.try_collect()
+				.await?;
+			let query_time = timer.elapsed();
+
+			Ok(RoomMessageEventContent::notice_markdown(format!(
+				"Query completed in {query_time:?}:\n\n```rs\n{results:#?}\n```"
+			)))
+		},
+	}
+}
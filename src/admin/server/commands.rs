@@ -117,6 +117,15 @@ pub(super) async fn list_database_files(&self) -> Result<RoomMessageEventContent
 	Ok(RoomMessageEventContent::notice_markdown(result))
 }
 
+#[admin_command]
+pub(super) async fn rotate_signing_key(&self) -> Result<RoomMessageEventContent> {
+	let (old_key_id, new_key_id) = self.services.server_keys.rotate_signing_key().await?;
+
+	Ok(RoomMessageEventContent::notice_plain(format!(
+		"Signing key {old_key_id} retired. Now signing with {new_key_id}."
+	)))
+}
+
 #[admin_command]
 pub(super) async fn admin_notice(&self, message: Vec<String>) -> Result<RoomMessageEventContent> {
 	let message = message.join(" ");
@@ -156,3 +165,22 @@ pub(super) async fn shutdown(&self) -> Result<RoomMessageEventContent> {
 
 	Ok(RoomMessageEventContent::notice_plain("Shutting down server..."))
 }
+
+#[admin_command]
+pub(super) async fn read_only(&self, disable: bool) -> Result<RoomMessageEventContent> {
+	let enable = !disable;
+	if self.services.server.set_read_only(enable) == enable {
+		return Ok(RoomMessageEventContent::notice_plain(format!(
+			"Read-only mode is already {}.",
+			if enable { "enabled" } else { "disabled" }
+		)));
+	}
+
+	warn!("read-only mode {}", if enable { "enabled" } else { "disabled" });
+
+	Ok(RoomMessageEventContent::notice_plain(if enable {
+		"Server is now read-only; writes will be refused until disabled."
+	} else {
+		"Server is writable again."
+	}))
+}
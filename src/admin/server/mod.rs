@@ -42,6 +42,12 @@ pub(super) enum ServerCommand {
 	/// - List database files
 	ListDatabaseFiles,
 
+	/// - Replace the server's signing key with a new one
+	///
+	/// The new key signs everything from then on. The current key stays
+	/// published as an old verify key so events it signed remain verifiable.
+	RotateSigningKey,
+
 	/// - Send a message to the admin room.
 	AdminNotice {
 		message: Vec<String>,
@@ -60,4 +66,17 @@ pub(super) enum ServerCommand {
 
 	/// - Shutdown the server
 	Shutdown,
+
+	/// - Put the server into read-only maintenance mode
+	///
+	/// Refused with a 503 while reads keep working: new events outside the
+	/// admin room, joins and leaves, registration, account data, to-device
+	/// messages, key and media uploads, profile changes, receipts, event
+	/// reports, and incoming federation transactions, joins, leaves and
+	/// invites. The admin room remains writable. Not persisted across restarts.
+	ReadOnly {
+		/// Leave read-only mode and accept writes again
+		#[arg(long)]
+		disable: bool,
+	},
 }
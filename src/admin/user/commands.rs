@@ -20,6 +20,7 @@ use ruma::{
 	},
 	EventId, OwnedRoomId, OwnedRoomOrAliasId, OwnedUserId, RoomId, UserId,
 };
+use service::users::ImportedUser;
 
 use crate::{
 	admin_command, get_room_info,
@@ -46,6 +47,44 @@ pub(super) async fn list_users(&self) -> Result<RoomMessageEventContent> {
 	Ok(RoomMessageEventContent::notice_markdown(plain_msg))
 }
 
+#[admin_command]
+pub(super) async fn import_users(&self) -> Result<RoomMessageEventContent> {
+	if self.body.len() < 2
+		|| !self.body[0].trim().starts_with("```")
+		|| self.body.last().unwrap_or(&"").trim() != "```"
+	{
+		return Ok(RoomMessageEventContent::text_plain(
+			"Expected code block in command body. Add --help for details.",
+		));
+	}
+
+	let string = self.body[1..self.body.len().saturating_sub(1)].join("\n");
+	let entries: Vec<ImportedUser> = match serde_json::from_str(&string) {
+		| Ok(entries) => entries,
+		| Err(e) => {
+			return Ok(RoomMessageEventContent::text_plain(format!(
+				"Could not parse user list: {e}"
+			)));
+		},
+	};
+
+	let report = self.services.users.import(entries).await?;
+
+	let mut plain_msg = format!("Imported {} user account(s).", report.created.len());
+	for (reason, user_ids) in [
+		("Already registered", &report.conflicts),
+		("Not local to this server", &report.rejected),
+		("Historical user ID or missing or malformed password hash", &report.invalid),
+	] {
+		if !user_ids.is_empty() {
+			let user_ids: Vec<_> = user_ids.iter().map(ToString::to_string).collect();
+			write!(plain_msg, "\n{reason}, skipped:\n```\n{}\n```", user_ids.join("\n"))?;
+		}
+	}
+
+	Ok(RoomMessageEventContent::notice_markdown(plain_msg))
+}
+
 #[admin_command]
 pub(super) async fn create_user(
 	&self,
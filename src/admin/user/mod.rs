@@ -61,6 +61,17 @@ pub(super) enum UserCommand {
 	#[clap(alias = "list")]
 	ListUsers,
 
+	/// - Import accounts migrated from another homeserver
+	///
+	/// Existing accounts, historical user IDs and entries without a valid
+	/// Argon2 `password_hash` are skipped and reported instead of aborting
+	/// the import.
+	///
+	/// This command needs a JSON array of objects with `user_id`,
+	/// `password_hash` and optional `displayname` and `avatar_url` fields in a
+	/// Markdown code block below the command.
+	ImportUsers,
+
 	/// - Lists all the rooms (local and remote) that the specified user is
 	///   joined in
 	ListJoinedRooms {
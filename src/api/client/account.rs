@@ -3,7 +3,8 @@ use std::fmt::Write;
 use axum::extract::State;
 use axum_client_ip::InsecureClientIp;
 use conduwuit::{
-	debug_info, error, info, is_equal_to, utils, utils::ReadyExt, warn, Error, PduBuilder, Result,
+	debug_info, error, info, is_equal_to, utils, utils::ReadyExt, warn, Err, Error, PduBuilder,
+	Result,
 };
 use futures::{FutureExt, StreamExt};
 use register::RegistrationKind;
@@ -288,6 +289,16 @@ pub(crate) async fn register_route(
 
 	let password = if is_guest { None } else { body.password.as_deref() };
 
+	// Refuse before creating the account when its first device would exceed the
+	// limit, rather than leaving an account without a session behind.
+	if is_guest || !body.inhibit_login {
+		if let Some(max_devices) = services.users.device_limit_reached(&user_id).await {
+			return Err!(Request(Forbidden(
+				"Registration would exceed the maximum of {max_devices} devices per user."
+			)));
+		}
+	}
+
 	// Create user
 	services.users.create(&user_id, password)?;
 
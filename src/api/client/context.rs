@@ -5,12 +5,12 @@ use conduwuit::{
 	at, err, ref_at,
 	utils::{
 		future::TryExtExt,
-		stream::{BroadbandExt, ReadyExt, WidebandExt},
+		stream::{BroadbandExt, ReadyExt, TryReadyExt},
 		IterStream,
 	},
 	Err, Result,
 };
-use futures::{join, try_join, FutureExt, StreamExt, TryFutureExt};
+use futures::{try_join, FutureExt, StreamExt, TryFutureExt, TryStreamExt};
 use ruma::{
 	api::client::{context::get_context, filter::LazyLoadOptions},
 	events::StateEventType,
@@ -18,7 +18,9 @@ use ruma::{
 };
 
 use crate::{
-	client::message::{event_filter, ignored_filter, update_lazy, visibility_filter, LazySet},
+	client::message::{
+		bundle_edit, event_filter, ignored_filter, update_lazy, visibility_filter, LazySet,
+	},
 	Ruma,
 };
 
@@ -91,6 +93,8 @@ pub(crate) async fn get_context_route(
 		return Err!(Request(Forbidden("You don't have permission to view this event.")));
 	}
 
+	let (_, base_event) = bundle_edit(&services, (base_token, base_event), sender_user).await;
+
 	let events_before =
 		services
 			.rooms
@@ -105,20 +109,22 @@ pub(crate) async fn get_context_route(
 	let (events_before, events_after) = try_join!(events_before, events_after)?;
 
 	let events_before = events_before
-		.ready_filter_map(|item| event_filter(item, filter))
-		.wide_filter_map(|item| ignored_filter(&services, item, sender_user))
-		.wide_filter_map(|item| visibility_filter(&services, item, sender_user))
+		.ready_try_filter_map(|item| Ok(event_filter(item, filter)))
+		.try_filter_map(|item| ignored_filter(&services, item, sender_user).map(Ok))
+		.try_filter_map(|item| visibility_filter(&services, item, sender_user).map(Ok))
 		.take(limit / 2)
-		.collect();
+		.and_then(|item| bundle_edit(&services, item, sender_user).map(Ok))
+		.try_collect();
 
 	let events_after = events_after
-		.ready_filter_map(|item| event_filter(item, filter))
-		.wide_filter_map(|item| ignored_filter(&services, item, sender_user))
-		.wide_filter_map(|item| visibility_filter(&services, item, sender_user))
+		.ready_try_filter_map(|item| Ok(event_filter(item, filter)))
+		.try_filter_map(|item| ignored_filter(&services, item, sender_user).map(Ok))
+		.try_filter_map(|item| visibility_filter(&services, item, sender_user).map(Ok))
 		.take(limit / 2)
-		.collect();
+		.and_then(|item| bundle_edit(&services, item, sender_user).map(Ok))
+		.try_collect();
 
-	let (events_before, events_after): (Vec<_>, Vec<_>) = join!(events_before, events_after);
+	let (events_before, events_after): (Vec<_>, Vec<_>) = try_join!(events_before, events_after)?;
 
 	let state_at = events_after
 		.last()
@@ -0,0 +1,134 @@
+use axum::extract::State;
+use conduwuit::{err, Err, Result};
+use futures::StreamExt;
+use ruma::{
+	api::client::dehydrated_device::{
+		delete_dehydrated_device, get_dehydrated_device, get_events, put_dehydrated_device,
+	},
+	serde::Raw,
+};
+
+use crate::{service::users::DehydratedDevice, Ruma};
+
+/// # `PUT /_matrix/client/unstable/org.matrix.msc3814.v1/dehydrated_device`
+///
+/// Stores a dehydrated device for the sender user along with its keys,
+/// replacing any previous dehydrated device.
+pub(crate) async fn put_dehydrated_device_route(
+	State(services): State<crate::State>,
+	body: Ruma<put_dehydrated_device::unstable::Request>,
+) -> Result<put_dehydrated_device::unstable::Response> {
+	services.server.check_writable()?;
+
+	let sender_user = body.sender_user();
+	let device_id = body.body.device_id.clone();
+
+	let device = DehydratedDevice {
+		device_id: device_id.clone(),
+		device_data: body.body.device_data.json().to_owned(),
+	};
+
+	services
+		.users
+		.set_dehydrated_device(sender_user, device, &body.body.one_time_keys)
+		.await?;
+
+	for (key_id, fallback_key) in &body.body.fallback_keys {
+		services
+			.users
+			.add_fallback_key(sender_user, &device_id, key_id, fallback_key)
+			.await?;
+	}
+
+	services
+		.users
+		.add_device_keys(sender_user, &device_id, &body.body.device_keys)
+		.await;
+
+	Ok(put_dehydrated_device::unstable::Response { device_id })
+}
+
+/// # `GET /_matrix/client/unstable/org.matrix.msc3814.v1/dehydrated_device`
+///
+/// Returns the sender user's dehydrated device.
+pub(crate) async fn get_dehydrated_device_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_dehydrated_device::unstable::Request>,
+) -> Result<get_dehydrated_device::unstable::Response> {
+	let sender_user = body.sender_user();
+
+	let device = services
+		.users
+		.get_dehydrated_device(sender_user)
+		.await
+		.map_err(|_| err!(Request(NotFound("No dehydrated device is stored."))))?;
+
+	Ok(get_dehydrated_device::unstable::Response {
+		device_id: device.device_id,
+		device_data: Raw::from_json(device.device_data),
+	})
+}
+
+/// # `DELETE /_matrix/client/unstable/org.matrix.msc3814.v1/dehydrated_device`
+///
+/// Removes the sender user's dehydrated device along with its pending
+/// to-device events.
+pub(crate) async fn delete_dehydrated_device_route(
+	State(services): State<crate::State>,
+	body: Ruma<delete_dehydrated_device::unstable::Request>,
+) -> Result<delete_dehydrated_device::unstable::Response> {
+	services.server.check_writable()?;
+
+	let sender_user = body.sender_user();
+
+	let device = services
+		.users
+		.take_dehydrated_device(sender_user)
+		.await
+		.map_err(|_| err!(Request(NotFound("No dehydrated device is stored."))))?;
+
+	Ok(delete_dehydrated_device::unstable::Response { device_id: device.device_id })
+}
+
+/// # `POST /_matrix/client/unstable/org.matrix.msc3814.v1/dehydrated_device/{device_id}/events`
+///
+/// Returns the to-device events queued for the sender user's dehydrated
+/// device. Events up to the `next_batch` of the previous call are removed.
+pub(crate) async fn get_dehydrated_events_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_events::unstable::Request>,
+) -> Result<get_events::unstable::Response> {
+	let sender_user = body.sender_user();
+
+	if !services
+		.users
+		.get_dehydrated_device(sender_user)
+		.await
+		.is_ok_and(|device| device.device_id == body.device_id)
+	{
+		return Err!(Request(NotFound("No such dehydrated device.")));
+	}
+
+	if let Some(next_batch) = body.next_batch.as_deref() {
+		let until = next_batch
+			.parse()
+			.map_err(|_| err!(Request(InvalidParam("Invalid next_batch token."))))?;
+
+		services
+			.users
+			.remove_to_device_events(sender_user, &body.device_id, until)
+			.await;
+	}
+
+	let next_batch = services.globals.current_count()?;
+	let events = services
+		.users
+		.get_to_device_events(sender_user, &body.device_id)
+		.collect()
+		.await;
+
+	Ok(get_events::unstable::Response {
+		next_batch: Some(next_batch.to_string()),
+		events,
+	})
+}
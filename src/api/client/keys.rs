@@ -1,3 +1,5 @@
+mod tests;
+
 use std::collections::{BTreeMap, HashMap, HashSet};
 
 use axum::extract::State;
@@ -31,12 +33,15 @@ use crate::{
 /// Publish end-to-end encryption keys for the sender device.
 ///
 /// - Adds one time keys
+/// - Adds fallback keys, replacing any previous one of the same algorithm
 /// - If there are no device keys yet: Adds device keys (TODO: merge with
 ///   existing keys?)
 pub(crate) async fn upload_keys_route(
 	State(services): State<crate::State>,
 	body: Ruma<upload_keys::v3::Request>,
 ) -> Result<upload_keys::v3::Response> {
+	services.server.check_writable()?;
+
 	let (sender_user, sender_device) = body.sender();
 
 	for (key_id, one_time_key) in &body.one_time_keys {
@@ -46,6 +51,13 @@ pub(crate) async fn upload_keys_route(
 			.await?;
 	}
 
+	for (key_id, fallback_key) in &body.fallback_keys {
+		services
+			.users
+			.add_fallback_key(sender_user, sender_device, key_id, fallback_key)
+			.await?;
+	}
+
 	if let Some(device_keys) = &body.device_keys {
 		// TODO: merge this and the existing event?
 		// This check is needed to assure that signatures are kept
@@ -113,6 +125,8 @@ pub(crate) async fn upload_signing_keys_route(
 	State(services): State<crate::State>,
 	body: Ruma<upload_signing_keys::v3::Request>,
 ) -> Result<upload_signing_keys::v3::Response> {
+	services.server.check_writable()?;
+
 	let sender_user = body.sender_user.as_ref().expect("user is authenticated");
 	let sender_device = body.sender_device.as_ref().expect("user is authenticated");
 
@@ -169,6 +183,8 @@ pub(crate) async fn upload_signatures_route(
 	State(services): State<crate::State>,
 	body: Ruma<upload_signatures::v3::Request>,
 ) -> Result<upload_signatures::v3::Response> {
+	services.server.check_writable()?;
+
 	let sender_user = body.sender_user.as_ref().expect("user is authenticated");
 
 	for (user_id, keys) in &body.signed_keys {
@@ -295,52 +311,43 @@ where
 			continue;
 		}
 
-		if device_ids.is_empty() {
-			let mut container = BTreeMap::new();
-			let mut devices = services.users.all_device_ids(user_id).boxed();
-
-			while let Some(device_id) = devices.next().await {
-				if let Ok(mut keys) = services.users.get_device_keys(user_id, device_id).await {
-					let metadata = services
-						.users
-						.get_device_metadata(user_id, device_id)
-						.await
-						.map_err(|_| {
-							err!(Database("all_device_keys contained nonexistent device."))
-						})?;
+		let user_devices = if services.users.exists(user_id).await {
+			Some(
+				services
+					.users
+					.all_device_ids(user_id)
+					.map(ToOwned::to_owned)
+					.collect()
+					.await,
+			)
+		} else {
+			None
+		};
 
-					add_unsigned_device_display_name(&mut keys, metadata, include_display_names)
-						.map_err(|_| err!(Database("invalid device keys in database")))?;
+		let Some(devices) = requested_devices(device_ids, user_devices) else {
+			continue;
+		};
 
-					container.insert(device_id.to_owned(), keys);
-				}
-			}
+		let mut container = BTreeMap::new();
+		for device_id in &devices {
+			if let Ok(mut keys) = services.users.get_device_keys(user_id, device_id).await {
+				let metadata = services
+					.users
+					.get_device_metadata(user_id, device_id)
+					.await
+					.map_err(|_| {
+						err!(Database("all_device_keys contained nonexistent device."))
+					})?;
 
-			device_keys.insert(user_id.to_owned(), container);
-		} else {
-			for device_id in device_ids {
-				let mut container = BTreeMap::new();
-				if let Ok(mut keys) = services.users.get_device_keys(user_id, device_id).await {
-					let metadata = services
-						.users
-						.get_device_metadata(user_id, device_id)
-						.await
-						.map_err(|_| {
-							err!(Request(InvalidParam(
-								"Tried to get keys for nonexistent device."
-							)))
-						})?;
-
-					add_unsigned_device_display_name(&mut keys, metadata, include_display_names)
-						.map_err(|_| err!(Database("invalid device keys in database")))?;
-
-					container.insert(device_id.to_owned(), keys);
-				}
+				add_unsigned_device_display_name(&mut keys, metadata, include_display_names)
+					.map_err(|_| err!(Database("invalid device keys in database")))?;
 
-				device_keys.insert(user_id.to_owned(), container);
+				container.insert(device_id.to_owned(), keys);
 			}
 		}
 
+		device_keys.insert(user_id.to_owned(), container);
+
 		if let Ok(master_key) = services
 			.users
 			.get_master_key(sender_user, user_id, &allowed_signatures)
@@ -452,6 +459,20 @@ fn add_unsigned_device_display_name(
 	Ok(())
 }
 
+/// The devices of a local user to return keys for: those requested, or all of
+/// them when none are. Users without an account are left out of the response.
+fn requested_devices(
+	requested: &[OwnedDeviceId],
+	user_devices: Option<Vec<OwnedDeviceId>>,
+) -> Option<Vec<OwnedDeviceId>> {
+	let mut devices = user_devices?;
+	if !requested.is_empty() {
+		devices.retain(|device_id| requested.contains(device_id));
+	}
+
+	Some(devices)
+}
+
 pub(crate) async fn claim_keys_helper(
 	services: &Services,
 	one_time_keys_input: &BTreeMap<OwnedUserId, BTreeMap<OwnedDeviceId, OneTimeKeyAlgorithm>>,
@@ -472,7 +493,7 @@ pub(crate) async fn claim_keys_helper(
 		for (device_id, key_algorithm) in map {
 			if let Ok(one_time_keys) = services
 				.users
-				.take_one_time_key(user_id, device_id, key_algorithm)
+				.claim_one_time_key(user_id, device_id, key_algorithm)
 				.await
 			{
 				let mut c = BTreeMap::new();
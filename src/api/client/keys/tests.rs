@@ -0,0 +1,44 @@
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+
+use ruma::{owned_device_id, owned_user_id, OwnedDeviceId, OwnedUserId};
+
+use super::requested_devices;
+
+#[test]
+fn query_two_users_one_unknown() {
+	let alice = owned_user_id!("@alice:example.com");
+	let bob = owned_user_id!("@bob:example.com");
+	let known: BTreeMap<OwnedUserId, Vec<OwnedDeviceId>> = [(alice.clone(), vec![
+		owned_device_id!("LAPTOP"),
+		owned_device_id!("PHONE"),
+		owned_device_id!("TABLET"),
+	])]
+	.into();
+
+	let query: BTreeMap<OwnedUserId, Vec<OwnedDeviceId>> = [
+		(alice.clone(), vec![owned_device_id!("PHONE"), owned_device_id!("LAPTOP")]),
+		(bob.clone(), vec![]),
+	]
+	.into();
+
+	let response: BTreeMap<_, _> = query
+		.iter()
+		.filter_map(|(user_id, requested)| {
+			requested_devices(requested, known.get(user_id).cloned())
+				.map(|devices| (user_id.clone(), devices))
+		})
+		.collect();
+
+	assert!(!response.contains_key(&bob));
+	assert_eq!(response[&alice], vec![owned_device_id!("LAPTOP"), owned_device_id!("PHONE")]);
+}
+
+#[test]
+fn query_without_devices_returns_all() {
+	let devices = vec![owned_device_id!("LAPTOP"), owned_device_id!("PHONE")];
+
+	assert_eq!(requested_devices(&[], Some(devices.clone())), Some(devices));
+	assert_eq!(requested_devices(&[], None), None);
+}
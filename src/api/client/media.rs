@@ -52,10 +52,14 @@ pub(crate) async fn create_content_route(
 	InsecureClientIp(client): InsecureClientIp,
 	body: Ruma<create_content::v3::Request>,
 ) -> Result<create_content::v3::Response> {
+	services.server.check_writable()?;
+
 	let user = body.sender_user.as_ref().expect("user is authenticated");
 
 	let filename = body.filename.as_deref();
 	let content_type = body.content_type.as_deref();
+	services.media.check_upload_content_type(content_type)?;
+
 	let content_disposition = make_content_disposition(None, content_type, filename);
 	let mxc = Mxc {
 		server_name: services.globals.server_name(),
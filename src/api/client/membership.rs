@@ -1,3 +1,5 @@
+mod tests;
+
 use std::{
 	collections::{BTreeMap, HashMap, HashSet},
 	net::IpAddr,
@@ -20,8 +22,9 @@ use ruma::{
 		client::{
 			error::ErrorKind,
 			membership::{
-				ban_user, forget_room, get_member_events, invite_user, join_room_by_id,
-				join_room_by_id_or_alias,
+				ban_user, forget_room,
+				get_member_events::{self, v3::MembershipEventFilter},
+				invite_user, join_room_by_id, join_room_by_id_or_alias,
 				joined_members::{self, v3::RoomMember},
 				joined_rooms, kick_user, leave_room, unban_user, ThirdPartySigned,
 			},
@@ -35,7 +38,7 @@ use ruma::{
 			member::{MembershipState, RoomMemberEventContent},
 			message::RoomMessageEventContent,
 		},
-		StateEventType,
+		StateEventType, TimelineEventType,
 	},
 	state_res, CanonicalJsonObject, CanonicalJsonValue, OwnedRoomId, OwnedServerName,
 	OwnedUserId, RoomId, RoomVersionId, ServerName, UserId,
@@ -67,11 +70,10 @@ async fn banned_room_check(
 
 	if let Some(room_id) = room_id {
 		if services.rooms.metadata.is_banned(room_id).await
-			|| services
+			|| !services
 				.globals
 				.config
-				.forbidden_remote_server_names
-				.contains(&room_id.server_name().unwrap().to_owned())
+				.federation_allowed(room_id.server_name().unwrap())
 		{
 			warn!(
 				"User {user_id} who is not an admin attempted to send an invite for or \
@@ -108,12 +110,7 @@ async fn banned_room_check(
 			return Err!(Request(Forbidden("This room is banned on this homeserver.")));
 		}
 	} else if let Some(server_name) = server_name {
-		if services
-			.globals
-			.config
-			.forbidden_remote_server_names
-			.contains(&server_name.to_owned())
-		{
+		if !services.globals.config.federation_allowed(server_name) {
 			warn!(
 				"User {user_id} who is not an admin tried joining a room which has the server \
 				 name {server_name} that is globally forbidden. Rejecting.",
@@ -607,10 +604,10 @@ pub(crate) async fn joined_rooms_route(
 
 /// # `POST /_matrix/client/r0/rooms/{roomId}/members`
 ///
-/// Lists all joined users in a room (TODO: at a specific point in time, with a
-/// specific membership).
+/// Lists all joined users in a room (TODO: at a specific point in time).
 ///
 /// - Only works if the user is currently joined
+/// - Member events can be filtered by `membership` and `not_membership`
 pub(crate) async fn get_member_events_route(
 	State(services): State<crate::State>,
 	body: Ruma<get_member_events::v3::Request>,
@@ -630,15 +627,32 @@ pub(crate) async fn get_member_events_route(
 		chunk: services
 			.rooms
 			.state_accessor
-			.room_state_full(&body.room_id)
+			.room_state_pdus(&body.room_id)
 			.await?
-			.iter()
-			.filter(|(key, _)| key.0 == StateEventType::RoomMember)
-			.map(|(_, pdu)| pdu.to_member_event())
-			.collect(),
+			.ready_filter(|pdu| pdu.kind == TimelineEventType::RoomMember)
+			.ready_filter(|pdu| {
+				membership_filter(pdu, body.membership.as_ref(), body.not_membership.as_ref())
+			})
+			.map(|pdu| pdu.to_member_event())
+			.collect()
+			.await,
 	})
 }
 
+fn membership_filter(
+	pdu: &PduEvent,
+	for_membership: Option<&MembershipEventFilter>,
+	not_membership: Option<&MembershipEventFilter>,
+) -> bool {
+	let Ok(content) = pdu.get_content::<RoomMemberEventContent>() else {
+		return false;
+	};
+
+	let membership = content.membership.as_str();
+	for_membership.is_none_or(|filter| filter.as_str() == membership)
+		&& not_membership.is_none_or(|filter| filter.as_str() != membership)
+}
+
 /// # `POST /_matrix/client/r0/rooms/{roomId}/joined_members`
 ///
 /// Lists all members of a room.
@@ -686,6 +700,8 @@ pub async fn join_room_by_id_helper(
 	third_party_signed: Option<&ThirdPartySigned>,
 	appservice_info: &Option<RegistrationInfo>,
 ) -> Result<join_room_by_id::v3::Response> {
+	services.server.check_writable()?;
+
 	let state_lock = services.rooms.state.mutex.lock(room_id).await;
 
 	let user_is_guest = services
@@ -1668,6 +1684,8 @@ async fn remote_leave_room(
 	user_id: &UserId,
 	room_id: &RoomId,
 ) -> Result<()> {
+	services.server.check_writable()?;
+
 	let mut make_leave_response_and_server =
 		Err!(BadServerResponse("No server available to assist in leaving."));
 
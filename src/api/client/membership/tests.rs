@@ -0,0 +1,92 @@
+#![cfg(test)]
+
+use conduwuit::PduEvent;
+use ruma::api::client::membership::get_member_events::v3::MembershipEventFilter;
+use serde_json::json;
+
+use super::membership_filter;
+
+fn member(user_id: &str, membership: &str) -> PduEvent {
+	let pdu = json!({
+		"event_id": "$event:example.org",
+		"room_id": "!room:example.org",
+		"sender": user_id,
+		"origin_server_ts": 1_700_000_000_000_u64,
+		"type": "m.room.member",
+		"state_key": user_id,
+		"content": { "membership": membership },
+		"prev_events": [],
+		"depth": 1,
+		"auth_events": [],
+		"hashes": { "sha256": "" },
+	});
+
+	// content is a RawValue, which only deserializes from JSON text
+	serde_json::from_str(&pdu.to_string()).expect("valid pdu")
+}
+
+fn members() -> Vec<PduEvent> {
+	vec![
+		member("@alice:example.org", "join"),
+		member("@bob:example.org", "invite"),
+		member("@carol:example.org", "leave"),
+		member("@dave:example.org", "ban"),
+		member("@erin:example.org", "join"),
+	]
+}
+
+fn filtered(
+	membership: Option<&MembershipEventFilter>,
+	not_membership: Option<&MembershipEventFilter>,
+) -> Vec<String> {
+	members()
+		.into_iter()
+		.filter(|pdu| membership_filter(pdu, membership, not_membership))
+		.map(|pdu| pdu.sender.to_string())
+		.collect()
+}
+
+#[test]
+fn membership_filter_unfiltered() {
+	assert_eq!(filtered(None, None).len(), 5);
+}
+
+#[test]
+fn membership_filter_only_membership() {
+	let invite = MembershipEventFilter::Invite;
+	assert_eq!(filtered(Some(&invite), None), ["@bob:example.org"]);
+
+	let join = MembershipEventFilter::Join;
+	assert_eq!(filtered(Some(&join), None), ["@alice:example.org", "@erin:example.org"]);
+}
+
+#[test]
+fn membership_filter_not_membership() {
+	let leave = MembershipEventFilter::Leave;
+	assert_eq!(filtered(None, Some(&leave)), [
+		"@alice:example.org",
+		"@bob:example.org",
+		"@dave:example.org",
+		"@erin:example.org",
+	]);
+}
+
+#[test]
+fn membership_filter_both() {
+	let join = MembershipEventFilter::Join;
+	let ban = MembershipEventFilter::Ban;
+	assert_eq!(filtered(Some(&join), Some(&ban)), ["@alice:example.org", "@erin:example.org"]);
+
+	// a membership excluded by not_membership is never listed
+	assert!(filtered(Some(&join), Some(&join)).is_empty());
+}
+
+#[test]
+fn membership_filter_skips_invalid_content() {
+	let pdu = member("@alice:example.org", "join");
+	let mut pdu = serde_json::to_value(pdu).expect("serializable pdu");
+	pdu["content"] = json!({ "membership": 42 });
+	let pdu: PduEvent = serde_json::from_str(&pdu.to_string()).expect("valid pdu");
+
+	assert!(!membership_filter(&pdu, None, None));
+}
@@ -5,12 +5,12 @@ use conduwuit::{
 	at, is_equal_to,
 	utils::{
 		result::{FlatOk, LogErr},
-		stream::{BroadbandExt, WidebandExt},
-		IterStream, ReadyExt,
+		stream::{BroadbandExt, TryReadyExt},
+		IterStream,
 	},
 	Event, PduCount, Result,
 };
-use futures::{FutureExt, StreamExt};
+use futures::{future::ready, FutureExt, StreamExt, TryStreamExt};
 use ruma::{
 	api::{
 		client::{filter::RoomEventFilter, message::get_message_events},
@@ -119,13 +119,14 @@ pub(crate) async fn get_message_events_route(
 	};
 
 	let events: Vec<_> = it
-		.ready_take_while(|(count, _)| Some(*count) != to)
-		.ready_filter_map(|item| event_filter(item, filter))
-		.wide_filter_map(|item| ignored_filter(&services, item, sender_user))
-		.wide_filter_map(|item| visibility_filter(&services, item, sender_user))
+		.try_take_while(|(count, _)| ready(Ok(Some(*count) != to)))
+		.ready_try_filter_map(|item| Ok(event_filter(item, filter)))
+		.try_filter_map(|item| ignored_filter(&services, item, sender_user).map(Ok))
+		.try_filter_map(|item| visibility_filter(&services, item, sender_user).map(Ok))
 		.take(limit)
-		.collect()
-		.await;
+		.and_then(|item| bundle_edit(&services, item, sender_user).map(Ok))
+		.try_collect()
+		.await?;
 
 	let lazy = events
 		.iter()
@@ -263,6 +264,23 @@ pub(crate) async fn visibility_filter(
 		.then_some(item)
 }
 
+/// Bundles the latest edit of the event into its unsigned `m.relations`.
+pub(crate) async fn bundle_edit(
+	services: &Services,
+	(count, mut pdu): PdusIterItem,
+	user_id: &UserId,
+) -> PdusIterItem {
+	services
+		.rooms
+		.pdu_metadata
+		.add_bundled_edit(user_id, &mut pdu)
+		.await
+		.log_err()
+		.ok();
+
+	(count, pdu)
+}
+
 pub(crate) fn event_filter(item: PdusIterItem, filter: &RoomEventFilter) -> Option<PdusIterItem> {
 	let (_, pdu) = &item;
 	pdu.matches(filter).then_some(item)
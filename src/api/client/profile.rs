@@ -34,6 +34,8 @@ pub(crate) async fn set_displayname_route(
 	State(services): State<crate::State>,
 	body: Ruma<set_display_name::v3::Request>,
 ) -> Result<set_display_name::v3::Response> {
+	services.server.check_writable()?;
+
 	let sender_user = body.sender_user.as_ref().expect("user is authenticated");
 
 	if *sender_user != body.user_id && body.appservice_info.is_none() {
@@ -123,6 +125,8 @@ pub(crate) async fn set_avatar_url_route(
 	State(services): State<crate::State>,
 	body: Ruma<set_avatar_url::v3::Request>,
 ) -> Result<set_avatar_url::v3::Response> {
+	services.server.check_writable()?;
+
 	let sender_user = body.sender_user.as_ref().expect("user is authenticated");
 
 	if *sender_user != body.user_id && body.appservice_info.is_none() {
@@ -24,6 +24,8 @@ pub(crate) async fn set_read_marker_route(
 	State(services): State<crate::State>,
 	body: Ruma<set_read_marker::v3::Request>,
 ) -> Result<set_read_marker::v3::Response> {
+	services.server.check_writable()?;
+
 	let sender_user = body.sender_user();
 
 	if let Some(event) = &body.fully_read {
@@ -113,6 +115,8 @@ pub(crate) async fn create_receipt_route(
 	State(services): State<crate::State>,
 	body: Ruma<create_receipt::v3::Request>,
 ) -> Result<create_receipt::v3::Response> {
+	services.server.check_writable()?;
+
 	let sender_user = body.sender_user();
 
 	if matches!(
@@ -83,6 +83,8 @@ pub(crate) async fn report_event_route(
 	InsecureClientIp(client): InsecureClientIp,
 	body: Ruma<report_content::v3::Request>,
 ) -> Result<report_content::v3::Response> {
+	services.server.check_writable()?;
+
 	// user authentication
 	let sender_user = body.sender_user.as_ref().expect("user is authenticated");
 
@@ -112,13 +114,21 @@ pub(crate) async fn report_event_route(
 	)
 	.await?;
 
+	let report_id = services.reports.db.report_event(
+		sender_user,
+		&pdu.room_id,
+		&pdu.event_id,
+		body.score,
+		body.reason.as_deref(),
+	)?;
+
 	// send admin room message that we received the report with an @room ping for
 	// urgency
 	services
 		.admin
 		.send_message(message::RoomMessageEventContent::text_markdown(format!(
-			"@room Event report received from {} -\n\nEvent ID: {}\nRoom ID: {}\nSent By: \
-			 {}\n\nReport Score: {}\nReport Reason: {}",
+			"@room Event report {report_id} received from {} -\n\nEvent ID: {}\nRoom ID: \
+			 {}\nSent By: {}\n\nReport Score: {}\nReport Reason: {}",
 			sender_user.to_owned(),
 			pdu.event_id,
 			pdu.room_id,
@@ -138,6 +148,7 @@ pub(crate) async fn report_event_route(
 /// check if score is in valid range
 /// check if report reasoning is less than or equal to 750 characters
 /// check if reporting user is in the reporting room
+/// check if reporting user is allowed to see the reported event
 async fn is_event_report_valid(
 	services: &Services,
 	event_id: &EventId,
@@ -186,6 +197,18 @@ async fn is_event_report_valid(
 		));
 	}
 
+	if !services
+		.rooms
+		.state_accessor
+		.user_can_see_event(sender_user, room_id, event_id)
+		.await
+	{
+		return Err(Error::BadRequest(
+			ErrorKind::NotFound,
+			"Event ID is not known to us or Event ID is invalid",
+		));
+	}
+
 	Ok(())
 }
 
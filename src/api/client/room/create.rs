@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 use axum::extract::State;
 use conduwuit::{debug_info, debug_warn, error, info, pdu::PduBuilder, warn, Err, Error, Result};
-use futures::FutureExt;
+use futures::{FutureExt, StreamExt};
 use ruma::{
 	api::client::{
 		error::ErrorKind,
@@ -25,7 +25,7 @@ use ruma::{
 	int,
 	serde::{JsonObject, Raw},
 	CanonicalJsonObject, Int, OwnedRoomAliasId, OwnedRoomId, OwnedUserId, RoomAliasId, RoomId,
-	RoomVersionId,
+	RoomVersionId, UserId,
 };
 use serde_json::{json, value::to_raw_value};
 use service::{appservice::RegistrationInfo, Services};
@@ -67,6 +67,17 @@ pub(crate) async fn create_room_route(
 		));
 	}
 
+	if let Some(max_rooms) = services.server.config.max_rooms_per_user {
+		if body.appservice_info.is_none()
+			&& !services.users.is_admin(sender_user).await
+			&& created_rooms_count(&services, sender_user).await >= max_rooms
+		{
+			return Err!(Request(Forbidden(
+				"You have reached the maximum of {max_rooms} created rooms."
+			)));
+		}
+	}
+
 	let room_id: OwnedRoomId = if let Some(custom_room_id) = &body.room_id {
 		custom_room_id_check(&services, custom_room_id)?
 	} else {
@@ -253,6 +264,7 @@ pub(crate) async fn create_room_route(
 	}
 
 	let power_levels_content = default_power_levels_content(
+		&services.server.config.default_power_levels,
 		body.power_level_content_override.as_ref(),
 		&body.visibility,
 		users,
@@ -466,8 +478,20 @@ pub(crate) async fn create_room_route(
 	Ok(create_room::v3::Response::new(room_id))
 }
 
+/// counts the rooms the user is joined to which they created themselves
+async fn created_rooms_count(services: &Services, user_id: &UserId) -> usize {
+	services
+		.rooms
+		.state_cache
+		.rooms_created(user_id)
+		.filter(|&room_id| services.rooms.state_cache.is_joined(user_id, room_id))
+		.count()
+		.await
+}
+
 /// creates the power_levels_content for the PDU builder
-fn default_power_levels_content(
+pub(super) fn default_power_levels_content(
+	template: &BTreeMap<String, serde_json::Value>,
 	power_level_content_override: Option<&Raw<RoomPowerLevelsEventContent>>,
 	visibility: &room::Visibility,
 	users: BTreeMap<OwnedUserId, Int>,
@@ -511,6 +535,25 @@ fn default_power_levels_content(
 			serde_json::to_value(50).expect("50 is valid Value");
 	}
 
+	// apply the server's configured power level template; the `events` and
+	// `users` maps are merged into our defaults instead of replacing them, and
+	// never change the levels of the creator or invited users
+	for (key, value) in template {
+		match (power_levels_content.get_mut(key), value) {
+			| (Some(serde_json::Value::Object(defaults)), serde_json::Value::Object(values))
+				if key == "events" =>
+				defaults.extend(values.clone()),
+			| (Some(serde_json::Value::Object(defaults)), serde_json::Value::Object(values))
+				if key == "users" =>
+				for (user_id, level) in values {
+					defaults
+						.entry(user_id.clone())
+						.or_insert_with(|| level.clone());
+				},
+			| _ => power_levels_content[key] = value.clone(),
+		}
+	}
+
 	if let Some(power_level_content_override) = power_level_content_override {
 		let json: JsonObject = serde_json::from_str(power_level_content_override.json().get())
 			.map_err(|_| {
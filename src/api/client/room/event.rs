@@ -3,7 +3,10 @@ use conduwuit::{err, Err, Event, Result};
 use futures::{try_join, FutureExt, TryFutureExt};
 use ruma::api::client::room::get_room_event;
 
-use crate::{client::ignored_filter, Ruma};
+use crate::{
+	client::{bundle_edit, ignored_filter},
+	Ruma,
+};
 
 /// # `GET /_matrix/client/r0/rooms/{roomId}/event/{eventId}`
 ///
@@ -30,7 +33,7 @@ pub(crate) async fn get_room_event_route(
 		.user_can_see_event(body.sender_user(), &body.room_id, &body.event_id)
 		.map(Ok);
 
-	let (token, mut event, visible) = try_join!(token, event, visible)?;
+	let (token, event, visible) = try_join!(token, event, visible)?;
 
 	if !visible
 		|| ignored_filter(&services, (token, event.clone()), body.sender_user())
@@ -44,6 +47,7 @@ pub(crate) async fn get_room_event_route(
 		return Err!(Request(NotFound("Event not found")));
 	}
 
+	let (_, mut event) = bundle_edit(&services, (token, event), body.sender_user()).await;
 	event.add_age().ok();
 
 	let event = event.to_room_event();
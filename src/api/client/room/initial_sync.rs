@@ -1,6 +1,6 @@
 use axum::extract::State;
 use conduwuit::{at, utils::BoolExt, Err, Result};
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 use ruma::api::client::room::initial_sync::v3::{PaginationChunk, Request, Response};
 
 use crate::Ruma;
@@ -29,8 +29,8 @@ pub(crate) async fn room_initial_sync_route(
 		.pdus_rev(None, room_id, None)
 		.await?
 		.take(limit)
-		.collect()
-		.await;
+		.try_collect()
+		.await?;
 
 	let state: Vec<_> = services
 		.rooms
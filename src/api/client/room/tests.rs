@@ -0,0 +1,72 @@
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+
+use ruma::{api::client::room::Visibility, int, owned_user_id, serde::Raw};
+use serde_json::{json, value::to_raw_value};
+
+use super::create::default_power_levels_content;
+
+fn template(value: serde_json::Value) -> BTreeMap<String, serde_json::Value> {
+	serde_json::from_value(value).expect("template is an object")
+}
+
+#[test]
+fn power_levels_template_applied() {
+	let creator = owned_user_id!("@alice:example.com");
+	let template = template(json!({
+		"invite": 50,
+		"events": { "m.room.name": 75 },
+		"users": { "@mod:example.com": 50 },
+	}));
+
+	let content = default_power_levels_content(
+		&template,
+		None,
+		&Visibility::Private,
+		BTreeMap::from([(creator, int!(100))]),
+	)
+	.expect("power levels");
+
+	assert_eq!(content["invite"], 50);
+	assert_eq!(content["events"]["m.room.name"], 75);
+	// merged into the built-in defaults rather than replacing them
+	assert_eq!(content["events"]["m.room.power_levels"], 100);
+	assert_eq!(content["users"]["@mod:example.com"], 50);
+	assert_eq!(content["users"]["@alice:example.com"], 100);
+}
+
+#[test]
+fn power_levels_template_keeps_creator() {
+	let creator = owned_user_id!("@alice:example.com");
+	let template = template(json!({ "users": { "@alice:example.com": 0 } }));
+
+	let content = default_power_levels_content(
+		&template,
+		None,
+		&Visibility::Private,
+		BTreeMap::from([(creator, int!(100))]),
+	)
+	.expect("power levels");
+
+	assert_eq!(content["users"]["@alice:example.com"], 100);
+}
+
+#[test]
+fn power_levels_override_beats_template() {
+	let creator = owned_user_id!("@alice:example.com");
+	let template = template(json!({ "invite": 50, "kick": 75 }));
+	let power_level_override =
+		Raw::from_json(to_raw_value(&json!({ "invite": 0 })).expect("valid json"));
+
+	let content = default_power_levels_content(
+		&template,
+		Some(&power_level_override),
+		&Visibility::Private,
+		BTreeMap::from([(creator, int!(100))]),
+	)
+	.expect("power levels");
+
+	assert_eq!(content["invite"], 0);
+	assert_eq!(content["kick"], 75);
+}
@@ -0,0 +1,47 @@
+use axum::extract::State;
+use conduwuit::{Err, Result};
+use ruma::{api::client::room::get_event_by_timestamp, MilliSecondsSinceUnixEpoch};
+
+use crate::Ruma;
+
+/// # `GET /_matrix/client/v1/rooms/{roomId}/timestamp_to_event`
+///
+/// Finds the event nearest to the given timestamp in the given direction.
+///
+/// - The user must be allowed to see the room's state
+/// - Only returns events the user is allowed to see
+pub(crate) async fn get_event_by_timestamp_route(
+	State(services): State<crate::State>,
+	ref body: Ruma<get_event_by_timestamp::v1::Request>,
+) -> Result<get_event_by_timestamp::v1::Response> {
+	let sender_user = body.sender_user();
+
+	if !services
+		.rooms
+		.state_accessor
+		.user_can_see_state_events(sender_user, &body.room_id)
+		.await
+	{
+		return Err!(Request(Forbidden("You don't have permission to view this room.")));
+	}
+
+	let pdu = services
+		.rooms
+		.timeline
+		.event_at_timestamp(&body.room_id, body.ts, &body.dir)
+		.await?;
+
+	if !services
+		.rooms
+		.state_accessor
+		.user_can_see_event(sender_user, &body.room_id, &pdu.event_id)
+		.await
+	{
+		return Err!(Request(NotFound("No event found near the given timestamp.")));
+	}
+
+	Ok(get_event_by_timestamp::v1::Response {
+		event_id: (*pdu.event_id).to_owned(),
+		origin_server_ts: MilliSecondsSinceUnixEpoch(pdu.origin_server_ts),
+	})
+}
@@ -5,7 +5,7 @@ use conduwuit::{
 	at, is_true,
 	result::FlatOk,
 	utils::{stream::ReadyExt, IterStream},
-	Err, PduEvent, Result,
+	Err, Result,
 };
 use futures::{future::OptionFuture, FutureExt, StreamExt, TryFutureExt};
 use ruma::{
@@ -181,13 +181,14 @@ async fn category_room_events(
 }
 
 async fn procure_room_state(services: &Services, room_id: &RoomId) -> Result<RoomState> {
-	let state_map = services
+	let state_events = services
 		.rooms
 		.state_accessor
-		.room_state_full(room_id)
-		.await?;
-
-	let state_events = state_map.values().map(PduEvent::to_state_event).collect();
+		.room_state_pdus(room_id)
+		.await?
+		.map(|pdu| pdu.to_state_event())
+		.collect()
+		.await;
 
 	Ok(state_events)
 }
@@ -1,3 +1,5 @@
+mod tests;
+
 use axum::extract::State;
 use axum_client_ip::InsecureClientIp;
 use conduwuit::{debug, err, info, utils::ReadyExt, warn, Err};
@@ -18,9 +20,15 @@ use ruma::{
 		},
 		uiaa::UserIdentifier,
 	},
-	UserId,
+	events::{
+		push_rules::{PushRulesEvent, PushRulesEventContent},
+		GlobalAccountDataEventType,
+	},
+	push::Ruleset,
+	ServerName, UserId,
 };
 use serde::Deserialize;
+use service::{appservice::RegistrationInfo, Services};
 
 use super::{DEVICE_ID_LENGTH, TOKEN_LENGTH};
 use crate::{utils, utils::hash, Error, Result, Ruma};
@@ -157,18 +165,14 @@ pub(crate) async fn login_route(
 				Error::BadRequest(ErrorKind::InvalidUsername, "Username is invalid.")
 			})?;
 
-			if let Some(ref info) = body.appservice_info {
-				if !info.is_user_match(&user_id) {
-					return Err(Error::BadRequest(
-						ErrorKind::Exclusive,
-						"User is not in namespace.",
-					));
-				}
-			} else {
-				return Err(Error::BadRequest(
-					ErrorKind::MissingToken,
-					"Missing appservice token.",
-				));
+			check_appservice_login(
+				body.appservice_info.as_ref(),
+				&user_id,
+				services.globals.server_name(),
+			)?;
+
+			if !services.users.exists(&user_id).await {
+				register_appservice_user(&services, &user_id).await?;
 			}
 
 			user_id
@@ -247,6 +251,55 @@ pub(crate) async fn login_route(
 	})
 }
 
+/// Checks that the appservice whose as_token authenticated the request may log
+/// in as `user_id`: a local user within the appservice's user namespace.
+fn check_appservice_login(
+	info: Option<&RegistrationInfo>,
+	user_id: &UserId,
+	server_name: &ServerName,
+) -> Result {
+	let Some(info) = info else {
+		return Err(Error::BadRequest(ErrorKind::MissingToken, "Missing appservice token."));
+	};
+
+	if user_id.server_name() != server_name {
+		return Err!(Request(Forbidden("User {user_id} does not belong to this server.")));
+	}
+
+	if !info.is_user_match(user_id) {
+		return Err(Error::BadRequest(ErrorKind::Exclusive, "User is not in namespace."));
+	}
+
+	Ok(())
+}
+
+/// Registers a user of an appservice's namespace the first time the appservice
+/// logs in as them, like `/register` does for appservices.
+async fn register_appservice_user(services: &Services, user_id: &UserId) -> Result {
+	services.users.create(user_id, None)?;
+	services
+		.users
+		.set_displayname(user_id, Some(user_id.localpart().to_owned()));
+
+	// Initial account data
+	services
+		.account_data
+		.update(
+			None,
+			user_id,
+			GlobalAccountDataEventType::PushRules.to_string().into(),
+			&serde_json::to_value(PushRulesEvent {
+				content: PushRulesEventContent { global: Ruleset::server_default(user_id) },
+			})
+			.expect("to json always works"),
+		)
+		.await?;
+
+	info!("Registered appservice user {user_id} on login");
+
+	Ok(())
+}
+
 /// # `POST /_matrix/client/v3/logout`
 ///
 /// Log out the current device.
@@ -0,0 +1,49 @@
+#![cfg(test)]
+
+use ruma::{api::appservice::Registration, server_name, user_id};
+use serde_json::json;
+use service::appservice::RegistrationInfo;
+
+use super::check_appservice_login;
+
+fn bridge() -> RegistrationInfo {
+	let registration: Registration = serde_json::from_value(json!({
+		"id": "bridge",
+		"url": "http://localhost:9000",
+		"as_token": "as_token",
+		"hs_token": "hs_token",
+		"sender_localpart": "bridgebot",
+		"namespaces": {
+			"users": [{ "exclusive": true, "regex": "@bridge_.*:example\\.com" }],
+			"aliases": [],
+			"rooms": [],
+		},
+	}))
+	.expect("valid registration");
+
+	registration.try_into().expect("valid namespaces")
+}
+
+#[test]
+fn appservice_login_in_namespace() {
+	let bridge = bridge();
+	let server_name = server_name!("example.com");
+
+	check_appservice_login(Some(&bridge), user_id!("@bridge_alice:example.com"), server_name)
+		.expect("namespaced user");
+	check_appservice_login(Some(&bridge), user_id!("@bridgebot:example.com"), server_name)
+		.expect("appservice sender");
+}
+
+#[test]
+fn appservice_login_outside_namespace() {
+	let bridge = bridge();
+	let server_name = server_name!("example.com");
+
+	check_appservice_login(Some(&bridge), user_id!("@alice:example.com"), server_name)
+		.expect_err("user outside the namespace");
+	check_appservice_login(Some(&bridge), user_id!("@bridge_alice:remote.example"), server_name)
+		.expect_err("remote user");
+	check_appservice_login(None, user_id!("@bridge_alice:example.com"), server_name)
+		.expect_err("no as_token");
+}
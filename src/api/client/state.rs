@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
 use axum::extract::State;
-use conduwuit::{err, pdu::PduBuilder, utils::BoolExt, Err, Error, PduEvent, Result};
+use conduwuit::{err, pdu::PduBuilder, utils::BoolExt, Err, Error, Result};
+use futures::StreamExt;
 use ruma::{
 	api::client::{
 		error::ErrorKind,
@@ -12,6 +13,7 @@ use ruma::{
 			canonical_alias::RoomCanonicalAliasEventContent,
 			history_visibility::{HistoryVisibility, RoomHistoryVisibilityEventContent},
 			join_rules::{JoinRule, RoomJoinRulesEventContent},
+			pinned_events::RoomPinnedEventsEventContent,
 		},
 		AnyStateEventContent, StateEventType,
 	},
@@ -30,6 +32,8 @@ use crate::{Ruma, RumaResponse};
 /// - Tries to send the event into the room, auth rules will determine if it is
 ///   allowed
 /// - If event is new `canonical_alias`: Rejects if alias is incorrect
+/// - If event is new `pinned_events`: Rejects if a pinned event is not in the
+///   room
 pub(crate) async fn send_state_event_for_key_route(
 	State(services): State<crate::State>,
 	body: Ruma<send_state_event::v3::Request>,
@@ -63,6 +67,8 @@ pub(crate) async fn send_state_event_for_key_route(
 /// - Tries to send the event into the room, auth rules will determine if it is
 ///   allowed
 /// - If event is new `canonical_alias`: Rejects if alias is incorrect
+/// - If event is new `pinned_events`: Rejects if a pinned event is not in the
+///   room
 pub(crate) async fn send_state_event_for_empty_key_route(
 	State(services): State<crate::State>,
 	body: Ruma<send_state_event::v3::Request>,
@@ -97,11 +103,11 @@ pub(crate) async fn get_state_events_route(
 		room_state: services
 			.rooms
 			.state_accessor
-			.room_state_full(&body.room_id)
+			.room_state_pdus(&body.room_id)
 			.await?
-			.values()
-			.map(PduEvent::to_state_event)
-			.collect(),
+			.map(|pdu| pdu.to_state_event())
+			.collect()
+			.await,
 	})
 }
 
@@ -263,33 +269,23 @@ async fn allowed_to_send_state_event(
 			if let Ok(canonical_alias) =
 				serde_json::from_str::<RoomCanonicalAliasEventContent>(json.json().get())
 			{
-				let mut aliases = canonical_alias.alt_aliases.clone();
-
-				if let Some(alias) = canonical_alias.alias {
-					aliases.push(alias);
-				}
-
-				for alias in aliases {
-					if !services.globals.server_is_ours(alias.server_name()) {
-						return Err!(Request(Forbidden(
-							"canonical_alias must be for this server"
-						)));
-					}
-
-					if !services
-						.rooms
-						.alias
-						.resolve_local_alias(&alias)
-						.await
-						.is_ok_and(|room| room == room_id)
-					// Make sure it's the right room
-					{
-						return Err!(Request(Forbidden(
-							"You are only allowed to send canonical_alias events when its \
-							 aliases already exist"
-						)));
-					}
-				}
+				services
+					.rooms
+					.alias
+					.check_canonical_alias(room_id, &canonical_alias)
+					.await?;
+			}
+		},
+		// only allow pinning events that we know belong to this room
+		| StateEventType::RoomPinnedEvents => {
+			if let Ok(pinned_events) =
+				serde_json::from_str::<RoomPinnedEventsEventContent>(json.json().get())
+			{
+				services
+					.rooms
+					.state_accessor
+					.check_pinned_events(room_id, &pinned_events.pinned)
+					.await?;
 			}
 		},
 		| _ => (),
@@ -1,12 +1,14 @@
+mod tests;
 mod v3;
 mod v4;
 
 use conduwuit::{
-	utils::stream::{BroadbandExt, ReadyExt},
+	result::LogErr,
+	utils::stream::{BroadbandExt, IterStream, ReadyExt},
 	PduCount,
 };
-use futures::StreamExt;
-use ruma::{RoomId, UserId};
+use futures::{future::ready, StreamExt, TryStreamExt};
+use ruma::{DeviceId, OwnedTransactionId, RoomId, UserId};
 
 pub(crate) use self::{v3::sync_events_route, v4::sync_events_v4_route};
 use crate::{service::Services, Error, PduEvent, Result};
@@ -14,6 +16,7 @@ use crate::{service::Services, Error, PduEvent, Result};
 async fn load_timeline(
 	services: &Services,
 	sender_user: &UserId,
+	sender_device: &DeviceId,
 	room_id: &RoomId,
 	roomsincecount: PduCount,
 	next_batch: Option<PduCount>,
@@ -34,26 +37,73 @@ async fn load_timeline(
 		.timeline
 		.pdus_rev(Some(sender_user), room_id, None)
 		.await?
-		.ready_skip_while(|&(pducount, _)| pducount > next_batch.unwrap_or_else(PduCount::max))
-		.ready_take_while(|&(pducount, _)| pducount > roomsincecount);
+		.try_skip_while(|&(pducount, _)| {
+			ready(Ok(pducount > next_batch.unwrap_or_else(PduCount::max)))
+		})
+		.try_take_while(|&(pducount, _)| ready(Ok(pducount > roomsincecount)));
 
 	// Take the last events for the timeline
 	let timeline_pdus: Vec<_> = non_timeline_pdus
 		.by_ref()
 		.take(limit)
-		.collect::<Vec<_>>()
-		.await
+		.try_collect::<Vec<_>>()
+		.await?
 		.into_iter()
 		.rev()
-		.collect();
+		.stream()
+		.then(|(pducount, mut pdu)| async move {
+			if pdu.sender == sender_user
+				&& !is_sent_by_device(services, sender_user, sender_device, &pdu).await
+			{
+				pdu.remove_transaction_id().log_err().ok();
+			}
+
+			services
+				.rooms
+				.pdu_metadata
+				.add_bundled_edit(sender_user, &mut pdu)
+				.await
+				.log_err()
+				.ok();
+
+			(pducount, pdu)
+		})
+		.collect()
+		.await;
 
 	// They /sync response doesn't always return all messages, so we say the output
 	// is limited unless there are events in non_timeline_pdus
-	let limited = non_timeline_pdus.next().await.is_some();
+	let limited = non_timeline_pdus.next().await.transpose()?.is_some();
 
 	Ok((timeline_pdus, limited))
 }
 
+/// Whether the transaction id in the event's unsigned data was used by this
+/// device to send it; only that device is shown the transaction id.
+async fn is_sent_by_device(
+	services: &Services,
+	sender_user: &UserId,
+	sender_device: &DeviceId,
+	pdu: &PduEvent,
+) -> bool {
+	let Ok(txn_id) = pdu.get_unsigned_property::<OwnedTransactionId>("transaction_id") else {
+		return false;
+	};
+
+	let sent = services
+		.transaction_ids
+		.existing_txnid(sender_user, Some(sender_device), &txn_id)
+		.await;
+
+	is_sent_event(pdu, sent.as_deref().ok())
+}
+
+/// Whether `pdu` is the event the device's transaction id was used to send,
+/// given the event id stored for that transaction id, if any.
+fn is_sent_event(pdu: &PduEvent, sent_event_id: Option<&[u8]>) -> bool {
+	sent_event_id.is_some_and(|event_id| event_id == pdu.event_id.as_bytes())
+}
+
 async fn share_encrypted_room(
 	services: &Services,
 	sender_user: &UserId,
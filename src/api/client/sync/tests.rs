@@ -0,0 +1,63 @@
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+
+use conduwuit::PduEvent;
+use ruma::{device_id, DeviceId, OwnedTransactionId};
+use serde_json::json;
+
+use super::is_sent_event;
+
+fn message(event_id: &str, txn_id: &str) -> PduEvent {
+	let pdu = json!({
+		"event_id": event_id,
+		"room_id": "!room:example.org",
+		"sender": "@alice:example.org",
+		"origin_server_ts": 1_700_000_000_000_u64,
+		"type": "m.room.message",
+		"content": { "msgtype": "m.text", "body": "hi" },
+		"unsigned": { "transaction_id": txn_id },
+		"prev_events": [],
+		"depth": 1,
+		"auth_events": [],
+		"hashes": { "sha256": "" },
+	});
+
+	// content is a RawValue, which only deserializes from JSON text
+	serde_json::from_str(&pdu.to_string()).expect("valid pdu")
+}
+
+/// Checks `pdu` for `device` the way is_sent_by_device() does, with the
+/// transaction ids each device used to send events.
+fn sent_by(device: &DeviceId, sent: &BTreeMap<(&DeviceId, &str), &str>, pdu: &PduEvent) -> bool {
+	let txn_id: OwnedTransactionId = pdu
+		.get_unsigned_property("transaction_id")
+		.expect("event has a transaction id");
+
+	let sent_event_id = sent
+		.get(&(device, txn_id.as_str()))
+		.map(|event_id| event_id.as_bytes());
+
+	is_sent_event(pdu, sent_event_id)
+}
+
+#[test]
+fn transaction_id_only_for_sending_device() {
+	let pdu = message("$message:example.org", "txn1");
+	let sent = BTreeMap::from([((device_id!("PHONE"), "txn1"), "$message:example.org")]);
+
+	assert!(sent_by(device_id!("PHONE"), &sent, &pdu));
+	assert!(!sent_by(device_id!("LAPTOP"), &sent, &pdu));
+}
+
+#[test]
+fn transaction_id_reused_by_other_device() {
+	let pdu = message("$message:example.org", "txn1");
+	let sent = BTreeMap::from([
+		((device_id!("PHONE"), "txn1"), "$message:example.org"),
+		((device_id!("LAPTOP"), "txn1"), "$other:example.org"),
+	]);
+
+	assert!(sent_by(device_id!("PHONE"), &sent, &pdu));
+	assert!(!sent_by(device_id!("LAPTOP"), &sent, &pdu));
+}
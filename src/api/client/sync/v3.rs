@@ -1,7 +1,9 @@
+mod tests;
+
 use std::{
 	cmp::{self},
 	collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
-	time::Duration,
+	time::{Duration, Instant},
 };
 
 use axum::extract::State;
@@ -24,7 +26,7 @@ use conduwuit_service::{
 };
 use futures::{
 	future::{join, join3, join4, join5, try_join, try_join3, OptionFuture},
-	FutureExt, StreamExt, TryFutureExt,
+	FutureExt, Stream, StreamExt, TryFutureExt,
 };
 use ruma::{
 	api::client::{
@@ -105,12 +107,15 @@ type PresenceUpdates = HashMap<OwnedUserId, PresenceEvent>;
 	skip_all,
 	fields(
 		since = %body.body.since.as_deref().unwrap_or_default(),
+		events = tracing::field::Empty,
+		elapsed = tracing::field::Empty,
     )
 )]
 pub(crate) async fn sync_events_route(
 	State(services): State<crate::State>,
 	body: Ruma<sync_events::v3::Request>,
 ) -> Result<sync_events::v3::Response, RumaResponse<UiaaResponse>> {
+	let timer = Instant::now();
 	let (sender_user, sender_device) = body.sender();
 
 	// Presence update
@@ -263,9 +268,14 @@ pub(crate) async fn sync_events_route(
 		.get_to_device_events(sender_user, sender_device)
 		.collect::<Vec<_>>();
 
-	let device_one_time_keys_count = services
-		.users
-		.count_one_time_keys(sender_user, sender_device);
+	let device_one_time_keys = join(
+		services
+			.users
+			.count_one_time_keys(sender_user, sender_device),
+		services
+			.users
+			.unused_fallback_key_types(sender_user, sender_device),
+	);
 
 	// Remove all to-device events the device received *last time*
 	let remove_to_device_events =
@@ -275,11 +285,12 @@ pub(crate) async fn sync_events_route(
 
 	let rooms = join3(joined_rooms, left_rooms, invited_rooms);
 	let ephemeral = join3(remove_to_device_events, to_device_events, presence_updates);
-	let top = join5(account_data, ephemeral, device_one_time_keys_count, keys_changed, rooms)
+	let top = join5(account_data, ephemeral, device_one_time_keys, keys_changed, rooms)
 		.boxed()
 		.await;
 
-	let (account_data, ephemeral, device_one_time_keys_count, keys_changed, rooms) = top;
+	let (account_data, ephemeral, device_one_time_keys, keys_changed, rooms) = top;
+	let (device_one_time_keys_count, device_unused_fallback_key_types) = device_one_time_keys;
 	let ((), to_device_events, presence_updates) = ephemeral;
 	let (joined_rooms, left_rooms, invited_rooms) = rooms;
 	let (joined_rooms, mut device_list_updates, left_encrypted_users) = joined_rooms;
@@ -308,8 +319,7 @@ pub(crate) async fn sync_events_route(
 			left: device_list_left.into_iter().collect(),
 		},
 		device_one_time_keys_count,
-		// Fallback keys are not yet supported
-		device_unused_fallback_key_types: None,
+		device_unused_fallback_key_types: Some(device_unused_fallback_key_types),
 		next_batch: next_batch_string,
 		presence: Presence {
 			events: presence_updates
@@ -327,6 +337,17 @@ pub(crate) async fn sync_events_route(
 		to_device: ToDevice { events: to_device_events },
 	};
 
+	let events: usize = response
+		.rooms
+		.join
+		.values()
+		.map(|joined_room| joined_room.timeline.events.len())
+		.sum();
+
+	let span = tracing::Span::current();
+	span.record("events", events);
+	span.record("elapsed", tracing::field::debug(timer.elapsed()));
+
 	// TODO: Retry the endpoint instead of returning
 	if !full_state
 		&& response.rooms.is_empty()
@@ -602,6 +623,7 @@ async fn load_joined_room(
 	let timeline = load_timeline(
 		services,
 		sender_user,
+		sender_device,
 		room_id,
 		sincecount,
 		Some(next_batchcount),
@@ -1223,71 +1245,53 @@ async fn calculate_counts(
 	let (joined_member_count, invited_member_count) =
 		join(joined_member_count, invited_member_count).await;
 
-	let small_room = joined_member_count.saturating_add(invited_member_count) > 5;
+	// Heroes are only used by clients to name rooms which have neither a name nor
+	// a canonical alias.
+	let (name, canonical_alias) = join(
+		services.rooms.state_accessor.get_name(room_id),
+		services.rooms.state_accessor.get_canonical_alias(room_id),
+	)
+	.await;
 
-	let heroes: OptionFuture<_> = small_room
+	let heroes: OptionFuture<_> = needs_heroes(name.is_ok(), canonical_alias.is_ok())
 		.then(|| calculate_heroes(services, room_id, sender_user))
 		.into();
 
 	Ok((Some(joined_member_count), Some(invited_member_count), heroes.await))
 }
 
-async fn calculate_heroes(
-	services: &Services,
-	room_id: &RoomId,
-	sender_user: &UserId,
-) -> Vec<OwnedUserId> {
-	services
-		.rooms
-		.timeline
-		.all_pdus(sender_user, room_id)
-		.ready_filter(|(_, pdu)| pdu.kind == RoomMember)
-		.fold_default(|heroes: Vec<_>, (_, pdu)| {
-			fold_hero(heroes, services, room_id, sender_user, pdu)
-		})
-		.await
+fn needs_heroes(has_name: bool, has_canonical_alias: bool) -> bool {
+	!has_name && !has_canonical_alias
 }
 
-async fn fold_hero(
-	mut heroes: Vec<OwnedUserId>,
+/// Picks up to five members other than the sender, joined members before
+/// invited ones. The order follows the membership index so the same heroes are
+/// chosen on every sync until the membership changes.
+async fn calculate_heroes(
 	services: &Services,
 	room_id: &RoomId,
 	sender_user: &UserId,
-	pdu: PduEvent,
 ) -> Vec<OwnedUserId> {
-	let Some(user_id): Option<&UserId> =
-		pdu.state_key.as_deref().map(TryInto::try_into).flat_ok()
-	else {
-		return heroes;
-	};
-
-	if user_id == sender_user {
-		return heroes;
-	}
-
-	let Ok(content): Result<RoomMemberEventContent, _> = pdu.get_content() else {
-		return heroes;
-	};
-
-	// The membership was and still is invite or join
-	if !matches!(content.membership, MembershipState::Join | MembershipState::Invite) {
-		return heroes;
-	}
-
-	if heroes.iter().any(is_equal_to!(user_id)) {
-		return heroes;
-	}
+	let state_cache = &services.rooms.state_cache;
 
-	let (is_invited, is_joined) = join(
-		services.rooms.state_cache.is_invited(user_id, room_id),
-		services.rooms.state_cache.is_joined(user_id, room_id),
+	select_heroes(
+		state_cache.room_members(room_id),
+		state_cache.room_members_invited(room_id),
+		sender_user,
 	)
-	.await;
-
-	if !is_joined && is_invited {
-		return heroes;
-	}
+	.await
+}
 
-	heroes.push(user_id.to_owned());
-	heroes
+async fn select_heroes<'a, J, I>(joined: J, invited: I, sender_user: &UserId) -> Vec<OwnedUserId>
+where
+	J: Stream<Item = &'a UserId> + Send,
+	I: Stream<Item = &'a UserId> + Send,
+{
+	joined
+		.chain(invited)
+		.ready_filter(|user_id| *user_id != sender_user)
+		.map(ToOwned::to_owned)
+		.take(5)
+		.collect()
+		.await
 }
@@ -0,0 +1,62 @@
+#![cfg(test)]
+
+use conduwuit::utils::IterStream;
+use ruma::{owned_user_id, user_id, UserId};
+
+use super::{needs_heroes, select_heroes};
+
+#[test]
+fn heroes_only_for_unnamed_rooms() {
+	assert!(needs_heroes(false, false));
+	assert!(!needs_heroes(true, false));
+	assert!(!needs_heroes(false, true));
+	assert!(!needs_heroes(true, true));
+}
+
+#[tokio::test]
+async fn heroes_skip_sender() {
+	let sender = user_id!("@alice:example.org");
+	let joined = [sender, user_id!("@bob:example.org")];
+	let invited: [&UserId; 0] = [];
+
+	let heroes =
+		select_heroes(joined.into_iter().stream(), invited.into_iter().stream(), sender).await;
+	assert_eq!(heroes, [owned_user_id!("@bob:example.org")]);
+}
+
+#[tokio::test]
+async fn heroes_include_invited_after_joined() {
+	let sender = user_id!("@alice:example.org");
+	let joined = [sender, user_id!("@bob:example.org")];
+	let invited = [user_id!("@carol:example.org")];
+
+	let heroes =
+		select_heroes(joined.into_iter().stream(), invited.into_iter().stream(), sender).await;
+	assert_eq!(heroes, [
+		owned_user_id!("@bob:example.org"),
+		owned_user_id!("@carol:example.org")
+	]);
+}
+
+#[tokio::test]
+async fn heroes_capped_at_five() {
+	let sender = user_id!("@alice:example.org");
+	let joined = [
+		user_id!("@bob:example.org"),
+		sender,
+		user_id!("@carol:example.org"),
+		user_id!("@dave:example.org"),
+		user_id!("@erin:example.org"),
+	];
+	let invited = [user_id!("@frank:example.org"), user_id!("@grace:example.org")];
+
+	let heroes =
+		select_heroes(joined.into_iter().stream(), invited.into_iter().stream(), sender).await;
+	assert_eq!(heroes, [
+		owned_user_id!("@bob:example.org"),
+		owned_user_id!("@carol:example.org"),
+		owned_user_id!("@dave:example.org"),
+		owned_user_id!("@erin:example.org"),
+		owned_user_id!("@frank:example.org"),
+	]);
+}
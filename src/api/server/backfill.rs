@@ -5,7 +5,7 @@ use conduwuit::{
 	utils::{IterStream, ReadyExt},
 	PduCount, Result,
 };
-use futures::{FutureExt, StreamExt};
+use futures::{FutureExt, StreamExt, TryStreamExt};
 use ruma::{api::federation::backfill::get_backfill, uint, MilliSecondsSinceUnixEpoch};
 
 use super::AccessCheck;
@@ -48,17 +48,23 @@ pub(crate) async fn get_backfill_route(
 		.ready_fold(PduCount::min(), cmp::max)
 		.await;
 
+	let pdus: Vec<_> = services
+		.rooms
+		.timeline
+		.pdus_rev(None, &body.room_id, Some(from.saturating_add(1)))
+		.await?
+		.take(limit)
+		.try_collect()
+		.await?;
+
 	Ok(get_backfill::v1::Response {
 		origin_server_ts: MilliSecondsSinceUnixEpoch::now(),
 
 		origin: services.globals.server_name().to_owned(),
 
-		pdus: services
-			.rooms
-			.timeline
-			.pdus_rev(None, &body.room_id, Some(from.saturating_add(1)))
-			.await?
-			.take(limit)
+		pdus: pdus
+			.into_iter()
+			.stream()
 			.filter_map(|(_, pdu)| async move {
 				services
 					.rooms
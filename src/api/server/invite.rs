@@ -20,6 +20,8 @@ pub(crate) async fn create_invite_route(
 	InsecureClientIp(client): InsecureClientIp,
 	body: Ruma<create_invite::v2::Request>,
 ) -> Result<create_invite::v2::Response> {
+	services.server.check_writable()?;
+
 	// ACL check origin
 	services
 		.rooms
@@ -35,22 +37,12 @@ pub(crate) async fn create_invite_route(
 	}
 
 	if let Some(server) = body.room_id.server_name() {
-		if services
-			.globals
-			.config
-			.forbidden_remote_server_names
-			.contains(&server.to_owned())
-		{
+		if !services.globals.config.federation_allowed(server) {
 			return Err!(Request(Forbidden("Server is banned on this homeserver.")));
 		}
 	}
 
-	if services
-		.globals
-		.config
-		.forbidden_remote_server_names
-		.contains(body.origin())
-	{
+	if !services.globals.config.federation_allowed(body.origin()) {
 		warn!(
 			"Received federated/remote invite from banned server {} for room ID {}. Rejecting.",
 			body.origin(),
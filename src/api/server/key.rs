@@ -30,7 +30,7 @@ pub(crate) async fn get_server_keys_route(
 	let mut all_keys = services.server_keys.verify_keys_for(server_name).await;
 
 	let verify_keys = all_keys
-		.remove_entry(active_key_id)
+		.remove_entry(&active_key_id)
 		.expect("active verify_key is missing");
 
 	let old_verify_keys = all_keys
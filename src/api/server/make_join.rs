@@ -41,12 +41,7 @@ pub(crate) async fn create_join_event_template_route(
 		.acl_check(body.origin(), &body.room_id)
 		.await?;
 
-	if services
-		.globals
-		.config
-		.forbidden_remote_server_names
-		.contains(body.origin())
-	{
+	if !services.globals.config.federation_allowed(body.origin()) {
 		warn!(
 			"Server {} for remote user {} tried joining room ID {} which has a server name that \
 			 is globally forbidden. Rejecting.",
@@ -58,12 +53,7 @@ pub(crate) async fn create_join_event_template_route(
 	}
 
 	if let Some(server) = body.room_id.server_name() {
-		if services
-			.globals
-			.config
-			.forbidden_remote_server_names
-			.contains(&server.to_owned())
-		{
+		if !services.globals.config.federation_allowed(server) {
 			return Err!(Request(Forbidden(warn!(
 				"Room ID server name {server} is banned on this homeserver."
 			))));
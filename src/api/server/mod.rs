@@ -16,6 +16,7 @@ pub(super) mod send_join;
 pub(super) mod send_leave;
 pub(super) mod state;
 pub(super) mod state_ids;
+pub(super) mod timestamp;
 pub(super) mod user;
 pub(super) mod version;
 pub(super) mod well_known;
@@ -38,6 +39,7 @@ pub(super) use send_join::*;
 pub(super) use send_leave::*;
 pub(super) use state::*;
 pub(super) use state_ids::*;
+pub(super) use timestamp::*;
 pub(super) use user::*;
 pub(super) use version::*;
 pub(super) use well_known::*;
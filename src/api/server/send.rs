@@ -25,6 +25,7 @@ use ruma::{
 };
 use serde_json::value::RawValue as RawJsonValue;
 use service::{
+	rooms::event_handler::{check_event_depth, check_event_origin, check_event_timestamp},
 	sending::{EDU_LIMIT, PDU_LIMIT},
 	Services,
 };
@@ -45,6 +46,10 @@ pub(crate) async fn send_transaction_message_route(
 	InsecureClientIp(client): InsecureClientIp,
 	body: Ruma<send_transaction_message::v1::Request>,
 ) -> Result<send_transaction_message::v1::Response> {
+	// Refuse the whole transaction so the origin retries it once we are writable
+	// again, rather than acknowledging PDUs we did not persist.
+	services.server.check_writable()?;
+
 	if body.origin() != body.body.origin {
 		return Err!(Request(Forbidden(
 			"Not allowed to send transactions on behalf of other servers"
@@ -63,6 +68,29 @@ pub(crate) async fn send_transaction_message_route(
 		)));
 	}
 
+	// A retry of a transaction still being handled waits here for the first
+	// attempt, then replays its response.
+	let _txn_lock = services
+		.transaction_ids
+		.lock_server_txnid(body.origin(), &body.transaction_id)
+		.await;
+
+	if let Some(response) = services
+		.transaction_ids
+		.existing_server_txnid(body.origin(), &body.transaction_id)
+		.await
+	{
+		debug!(
+			id = ?body.transaction_id,
+			origin =?body.origin(),
+			"Replayed txn, returning previous response",
+		);
+
+		return Ok(send_transaction_message::v1::Response {
+			pdus: serde_json::from_slice(&response)?,
+		});
+	}
+
 	let txn_start_time = Instant::now();
 	trace!(
 		pdus = ?body.pdus.len(),
@@ -86,12 +114,18 @@ pub(crate) async fn send_transaction_message_route(
 		"Finished txn",
 	);
 
-	Ok(send_transaction_message::v1::Response {
-		pdus: resolved_map
-			.into_iter()
-			.map(|(e, r)| (e, r.map_err(error::sanitized_message)))
-			.collect(),
-	})
+	let pdus: BTreeMap<_, _> = resolved_map
+		.into_iter()
+		.map(|(e, r)| (e, r.map_err(error::sanitized_message)))
+		.collect();
+
+	services.transaction_ids.add_server_txnid(
+		body.origin(),
+		&body.transaction_id,
+		&serde_json::to_vec(&pdus)?,
+	);
+
+	Ok(send_transaction_message::v1::Response { pdus })
 }
 
 async fn handle_pdus(
@@ -115,9 +149,34 @@ async fn handle_pdus(
 		// and hashes checks
 	}
 
+	let max_skew = services
+		.server
+		.config
+		.max_event_future_skew_s
+		.saturating_mul(1000);
+	let max_depth_gap = services.server.config.max_event_depth_gap;
+
 	let mut resolved_map = BTreeMap::new();
 	for (event_id, value, room_id) in parsed_pdus {
 		services.server.check_running()?;
+		let now = utils::millis_since_unix_epoch();
+		let max_depth = services
+			.rooms
+			.state
+			.max_forward_extremity_depth(&room_id)
+			.await;
+		if let Err(e) = check_event_origin(origin, &value)
+			.and_then(|()| check_event_timestamp(&value, now, max_skew))
+			.and_then(|()| {
+				max_depth.map_or(Ok(()), |max_depth| {
+					check_event_depth(&value, max_depth, max_depth_gap)
+				})
+			}) {
+			debug_warn!("Rejecting PDU {event_id}: {e}");
+			resolved_map.insert(event_id, Err(e));
+			continue;
+		}
+
 		let pdu_start_time = Instant::now();
 		let mutex_lock = services
 			.rooms
@@ -31,6 +31,8 @@ async fn create_join_event(
 	room_id: &RoomId,
 	pdu: &RawJsonValue,
 ) -> Result<create_join_event::v1::RoomState> {
+	services.server.check_writable()?;
+
 	if !services.rooms.metadata.exists(room_id).await {
 		return Err!(Request(NotFound("Room is unknown to this server.")));
 	}
@@ -269,12 +271,7 @@ pub(crate) async fn create_join_event_v1_route(
 	State(services): State<crate::State>,
 	body: Ruma<create_join_event::v1::Request>,
 ) -> Result<create_join_event::v1::Response> {
-	if services
-		.globals
-		.config
-		.forbidden_remote_server_names
-		.contains(body.origin())
-	{
+	if !services.globals.config.federation_allowed(body.origin()) {
 		warn!(
 			"Server {} tried joining room ID {} through us who has a server name that is \
 			 globally forbidden. Rejecting.",
@@ -285,12 +282,7 @@ pub(crate) async fn create_join_event_v1_route(
 	}
 
 	if let Some(server) = body.room_id.server_name() {
-		if services
-			.globals
-			.config
-			.forbidden_remote_server_names
-			.contains(&server.to_owned())
-		{
+		if !services.globals.config.federation_allowed(server) {
 			warn!(
 				"Server {} tried joining room ID {} through us which has a server name that is \
 				 globally forbidden. Rejecting.",
@@ -317,22 +309,12 @@ pub(crate) async fn create_join_event_v2_route(
 	State(services): State<crate::State>,
 	body: Ruma<create_join_event::v2::Request>,
 ) -> Result<create_join_event::v2::Response> {
-	if services
-		.globals
-		.config
-		.forbidden_remote_server_names
-		.contains(body.origin())
-	{
+	if !services.globals.config.federation_allowed(body.origin()) {
 		return Err!(Request(Forbidden("Server is banned on this homeserver.")));
 	}
 
 	if let Some(server) = body.room_id.server_name() {
-		if services
-			.globals
-			.config
-			.forbidden_remote_server_names
-			.contains(&server.to_owned())
-		{
+		if !services.globals.config.federation_allowed(server) {
 			warn!(
 				"Server {} tried joining room ID {} through us which has a server name that is \
 				 globally forbidden. Rejecting.",
@@ -47,6 +47,8 @@ async fn create_leave_event(
 	room_id: &RoomId,
 	pdu: &RawJsonValue,
 ) -> Result {
+	services.server.check_writable()?;
+
 	if !services.rooms.metadata.exists(room_id).await {
 		return Err!(Request(NotFound("Room is unknown to this server.")));
 	}
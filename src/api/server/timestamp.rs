@@ -0,0 +1,43 @@
+use axum::extract::State;
+use conduwuit::{Err, Result};
+use ruma::{api::federation::event::get_event_by_timestamp, MilliSecondsSinceUnixEpoch};
+
+use super::AccessCheck;
+use crate::Ruma;
+
+/// # `GET /_matrix/federation/v1/timestamp_to_event/{roomId}`
+///
+/// Finds the event nearest to the given timestamp in the given direction.
+pub(crate) async fn get_event_by_timestamp_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_event_by_timestamp::v1::Request>,
+) -> Result<get_event_by_timestamp::v1::Response> {
+	AccessCheck {
+		services: &services,
+		origin: body.origin(),
+		room_id: &body.room_id,
+		event_id: None,
+	}
+	.check()
+	.await?;
+
+	let pdu = services
+		.rooms
+		.timeline
+		.event_at_timestamp(&body.room_id, body.ts, &body.dir)
+		.await?;
+
+	if !services
+		.rooms
+		.state_accessor
+		.server_can_see_event(body.origin(), &body.room_id, &pdu.event_id)
+		.await
+	{
+		return Err!(Request(NotFound("No event found near the given timestamp.")));
+	}
+
+	Ok(get_event_by_timestamp::v1::Response {
+		event_id: (*pdu.event_id).to_owned(),
+		origin_server_ts: MilliSecondsSinceUnixEpoch(pdu.origin_server_ts),
+	})
+}
@@ -1,7 +1,8 @@
-use std::env::consts::OS;
+use std::{collections::BTreeMap, env::consts::OS};
 
 use either::Either;
 use figment::Figment;
+use ruma::events::room::power_levels::RoomPowerLevelsEventContent;
 
 use super::DEPRECATED_KEYS;
 use crate::{debug, debug_info, error, info, warn, Config, Err, Result};
@@ -94,6 +95,14 @@ pub fn check(config: &Config) -> Result<()> {
 		));
 	}
 
+	// tokio intervals panic on a zero period
+	if config.retention_interval_s == 0 {
+		return Err!(Config(
+			"retention_interval_s",
+			"retention_interval_s cannot be 0. Please set a value at least 1."
+		));
+	}
+
 	// yeah, unless the user built a debug build hopefully for local testing only
 	if cfg!(not(debug_assertions)) && config.server_name == "your.server.name" {
 		return Err!(Config(
@@ -224,6 +233,8 @@ pub fn check(config: &Config) -> Result<()> {
 		);
 	}
 
+	check_default_power_levels(&config.default_power_levels)?;
+
 	if let Some(Either::Right(_)) = config.url_preview_bound_interface.as_ref() {
 		if !matches!(OS, "android" | "fuchsia" | "linux") {
 			return Err!(Config(
@@ -236,6 +247,22 @@ pub fn check(config: &Config) -> Result<()> {
 	Ok(())
 }
 
+/// Rejects a `default_power_levels` template which is not valid
+/// `m.room.power_levels` content, rather than failing every room creation.
+pub(super) fn check_default_power_levels(
+	template: &BTreeMap<String, serde_json::Value>,
+) -> Result<()> {
+	let template = serde_json::to_value(template).expect("map serializes to json");
+	if let Err(e) = serde_json::from_value::<RoomPowerLevelsEventContent>(template) {
+		return Err!(Config(
+			"default_power_levels",
+			"Not valid m.room.power_levels content: {e}"
+		));
+	}
+
+	Ok(())
+}
+
 /// Iterates over all the keys in the config file and warns if there is a
 /// deprecated key specified
 fn warn_deprecated(config: &Config) {
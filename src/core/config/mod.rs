@@ -1,5 +1,6 @@
 pub mod check;
 pub mod proxy;
+mod tests;
 
 use std::{
 	collections::{BTreeMap, BTreeSet, HashSet},
@@ -19,7 +20,7 @@ use itertools::Itertools;
 use regex::RegexSet;
 use ruma::{
 	api::client::discovery::discover_support::ContactRole, OwnedRoomOrAliasId, OwnedServerName,
-	OwnedUserId, RoomVersionId,
+	OwnedUserId, RoomVersionId, ServerName,
 };
 use serde::{de::IgnoredAny, Deserialize};
 use url::Url;
@@ -227,6 +228,12 @@ pub struct Config {
 	#[serde(default = "default_user_visibility_cache_capacity")]
 	pub user_visibility_cache_capacity: u32,
 
+	/// Number of rooms for which to cache whether encryption is enabled.
+	///
+	/// default: varies by system
+	#[serde(default = "default_encrypted_room_cache_capacity")]
+	pub encrypted_room_cache_capacity: u32,
+
 	/// default: varies by system
 	#[serde(default = "default_stateinfo_cache_capacity")]
 	pub stateinfo_cache_capacity: u32,
@@ -490,6 +497,24 @@ pub struct Config {
 	#[serde(default)]
 	pub federation_loopback: bool,
 
+	/// How far in the future (seconds) the `origin_server_ts` of an event
+	/// received over federation may be compared to our own clock before the
+	/// event is rejected. Events with timestamps far in the future can
+	/// otherwise disturb the ordering of events in a room.
+	///
+	/// default: 300
+	#[serde(default = "default_max_event_future_skew_s")]
+	pub max_event_future_skew_s: u64,
+
+	/// How far the `depth` of an event received over federation may exceed the
+	/// greatest depth among the room's current forward extremities before the
+	/// event is rejected. This defends against events with implausibly deep
+	/// chains; legitimate gaps are filled by fetching the missing events.
+	///
+	/// default: 100000
+	#[serde(default = "default_max_event_depth_gap")]
+	pub max_event_depth_gap: u64,
+
 	/// Set this to true to require authentication on the normally
 	/// unauthenticated profile retrieval endpoints (GET)
 	/// "/_matrix/client/v3/profile/{userId}".
@@ -555,6 +580,20 @@ pub struct Config {
 	#[serde(default = "true_fn")]
 	pub allow_room_creation: bool,
 
+	/// Maximum number of rooms a user may have created and still be joined
+	/// to. Creating another room past this is rejected. Appservices and admins
+	/// are exempt. No limit is applied if unset.
+	///
+	/// example: 100
+	pub max_rooms_per_user: Option<usize>,
+
+	/// Maximum number of devices (sessions) a user may have. Logging in with a
+	/// new device past this is rejected. Admins are exempt. No limit is
+	/// applied if unset.
+	///
+	/// example: 50
+	pub max_devices_per_user: Option<usize>,
+
 	/// Set to false to disable users from joining or creating room versions
 	/// that aren't 100% officially supported by conduwuit.
 	///
@@ -573,6 +612,18 @@ pub struct Config {
 	#[serde(default = "default_default_room_version")]
 	pub default_room_version: RoomVersionId,
 
+	/// Power levels applied to the `m.room.power_levels` event of every room
+	/// created on this server, on top of conduwuit's built-in defaults.
+	/// Top-level values replace the defaults, while the `events` and `users`
+	/// tables are merged into them. A client's `power_level_content_override`
+	/// still takes precedence over this.
+	///
+	/// example: { invite = 50, events = { "m.room.name" = 50 } }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub default_power_levels: BTreeMap<String, serde_json::Value>,
+
 	// external structure; separate section
 	#[serde(default)]
 	pub well_known: WellKnownConfig,
@@ -1130,6 +1181,26 @@ pub struct Config {
 	#[serde(default = "default_typing_client_timeout_max_s")]
 	pub typing_client_timeout_max_s: u64,
 
+	/// Set this to true to periodically purge the content of message events
+	/// older than their room's `m.room.retention` max_lifetime, or
+	/// `retention_default_max_lifetime_s` for rooms without a policy. Expired
+	/// events are redacted locally; state events are never purged.
+	#[serde(default)]
+	pub allow_retention: bool,
+
+	/// Maximum age in seconds of message events in rooms without an
+	/// `m.room.retention` policy. If unset, such rooms keep their history.
+	///
+	/// example: 31536000
+	pub retention_default_max_lifetime_s: Option<u64>,
+
+	/// How often in seconds to purge expired events when `allow_retention` is
+	/// enabled.
+	///
+	/// default: 86400
+	#[serde(default = "default_retention_interval_s")]
+	pub retention_interval_s: u64,
+
 	/// Set this to true for conduwuit to compress HTTP response bodies using
 	/// zstd. This option does nothing if conduwuit was not built with
 	/// `zstd_compression` feature. Please be aware that enabling HTTP
@@ -1233,6 +1304,23 @@ pub struct Config {
 	#[serde(default)]
 	pub prevent_media_downloads_from: HashSet<OwnedServerName>,
 
+	/// List of content types local users may upload. Entries are MIME types
+	/// such as "image/png" or wildcards such as "image/*". If empty, every
+	/// content type not listed in `forbidden_media_upload_content_types` is
+	/// allowed.
+	///
+	/// default: []
+	#[serde(default)]
+	pub allowed_media_upload_content_types: Vec<String>,
+
+	/// List of content types local users may not upload, for example
+	/// "application/x-msdownload". Entries take the same form as
+	/// `allowed_media_upload_content_types` and take precedence over it.
+	///
+	/// default: []
+	#[serde(default)]
+	pub forbidden_media_upload_content_types: Vec<String>,
+
 	/// List of forbidden server names that we will block incoming AND outgoing
 	/// federation with, and block client room joins / remote user invites.
 	///
@@ -1246,6 +1334,18 @@ pub struct Config {
 	#[serde(default)]
 	pub forbidden_remote_server_names: HashSet<OwnedServerName>,
 
+	/// List of server names that we will exclusively allow incoming AND
+	/// outgoing federation with. If empty, all servers are allowed unless they
+	/// are listed in `forbidden_remote_server_names`, which always takes
+	/// precedence over this list.
+	///
+	/// This check is applied on the inbound federation X-Matrix origin and
+	/// outbound federation handler.
+	///
+	/// default: []
+	#[serde(default)]
+	pub allowed_remote_server_names: HashSet<OwnedServerName>,
+
 	/// List of forbidden server names that we will block all outgoing federated
 	/// room directory requests for. Useful for preventing our users from
 	/// wandering into bad servers or spaces.
@@ -1693,6 +1793,20 @@ impl Config {
 	}
 
 	pub fn check(&self) -> Result<(), Error> { check(self) }
+
+	/// Whether federation with the given server is permitted by the
+	/// configured server name allow and deny lists. Our own server name is
+	/// always allowed unless it is explicitly forbidden.
+	#[must_use]
+	pub fn federation_allowed(&self, server_name: &ServerName) -> bool {
+		if self.forbidden_remote_server_names.contains(server_name) {
+			return false;
+		}
+
+		server_name == self.server_name
+			|| self.allowed_remote_server_names.is_empty()
+			|| self.allowed_remote_server_names.contains(server_name)
+	}
 }
 
 impl fmt::Display for Config {
@@ -1727,6 +1841,10 @@ impl fmt::Display for Config {
 			"User visibility cache capacity",
 			&self.user_visibility_cache_capacity.to_string(),
 		);
+		line(
+			"Encrypted room cache capacity",
+			&self.encrypted_room_cache_capacity.to_string(),
+		);
 		line("Stateinfo cache capacity", &self.stateinfo_cache_capacity.to_string());
 		line(
 			"Roomid space hierarchy cache capacity",
@@ -1794,6 +1912,8 @@ impl fmt::Display for Config {
 		line("Allow encryption", &self.allow_encryption.to_string());
 		line("Allow federation", &self.allow_federation.to_string());
 		line("Federation loopback", &self.federation_loopback.to_string());
+		line("Max event future skew", &self.max_event_future_skew_s.to_string());
+		line("Max event depth gap", &self.max_event_depth_gap.to_string());
 		line(
 			"Require authentication for profile requests",
 			&self.require_auth_for_profile_requests.to_string(),
@@ -1843,6 +1963,14 @@ impl fmt::Display for Config {
 		);
 		line("Client typing timeout minimum", &self.typing_client_timeout_min_s.to_string());
 		line("Client typing timeout maxmimum", &self.typing_client_timeout_max_s.to_string());
+		line("Allow event retention purging", &self.allow_retention.to_string());
+		line(
+			"Default event retention max lifetime",
+			&self
+				.retention_default_max_lifetime_s
+				.map_or_else(|| "unset".to_owned(), |s| s.to_string()),
+		);
+		line("Event retention purge interval", &self.retention_interval_s.to_string());
 		line("Allow device name federation", &self.allow_device_name_federation.to_string());
 		line(
 			"Allow incoming profile lookup federation requests",
@@ -1856,6 +1984,22 @@ impl fmt::Display for Config {
 		);
 		line("Notification push path", &self.notification_push_path);
 		line("Allow room creation", &self.allow_room_creation.to_string());
+		line(
+			"Max rooms per user",
+			&self
+				.max_rooms_per_user
+				.map_or_else(|| "unlimited".to_owned(), |max| max.to_string()),
+		);
+		line(
+			"Max devices per user",
+			&self
+				.max_devices_per_user
+				.map_or_else(|| "unlimited".to_owned(), |max| max.to_string()),
+		);
+		line(
+			"Default power levels",
+			&serde_json::to_string(&self.default_power_levels).unwrap_or_default(),
+		);
 		line(
 			"Allow public room directory over federation",
 			&self.allow_public_room_directory_over_federation.to_string(),
@@ -1977,6 +2121,14 @@ impl fmt::Display for Config {
 			}
 			&lst.join(", ")
 		});
+		line(
+			"Allowed media upload content types",
+			&self.allowed_media_upload_content_types.join(", "),
+		);
+		line(
+			"Forbidden media upload content types",
+			&self.forbidden_media_upload_content_types.join(", "),
+		);
 		line("Forbidden Remote Server Names (\"Global\" ACLs)", {
 			let mut lst = Vec::with_capacity(self.forbidden_remote_server_names.len());
 			for domain in &self.forbidden_remote_server_names {
@@ -1984,6 +2136,13 @@ impl fmt::Display for Config {
 			}
 			&lst.join(", ")
 		});
+		line("Allowed Remote Server Names", {
+			let mut lst = Vec::with_capacity(self.allowed_remote_server_names.len());
+			for domain in &self.allowed_remote_server_names {
+				lst.push(domain.host());
+			}
+			&lst.join(", ")
+		});
 		line("Forbidden Remote Room Directory Server Names", {
 			let mut lst =
 				Vec::with_capacity(self.forbidden_remote_room_directory_server_names.len());
@@ -2150,6 +2309,8 @@ fn default_server_visibility_cache_capacity() -> u32 { parallelism_scaled_u32(50
 
 fn default_user_visibility_cache_capacity() -> u32 { parallelism_scaled_u32(1000) }
 
+fn default_encrypted_room_cache_capacity() -> u32 { parallelism_scaled_u32(1000) }
+
 fn default_stateinfo_cache_capacity() -> u32 { parallelism_scaled_u32(100) }
 
 fn default_roomid_spacehierarchy_cache_capacity() -> u32 { parallelism_scaled_u32(1000) }
@@ -2186,6 +2347,10 @@ fn default_well_known_timeout() -> u64 { 10 }
 
 fn default_federation_timeout() -> u64 { 25 }
 
+fn default_max_event_future_skew_s() -> u64 { 300 }
+
+fn default_max_event_depth_gap() -> u64 { 100_000 }
+
 fn default_federation_idle_timeout() -> u64 { 25 }
 
 fn default_federation_idle_per_host() -> u16 { 1 }
@@ -2252,6 +2417,8 @@ fn default_typing_client_timeout_min_s() -> u64 { 15 }
 
 fn default_typing_client_timeout_max_s() -> u64 { 45 }
 
+fn default_retention_interval_s() -> u64 { 86400 }
+
 fn default_rocksdb_recovery_mode() -> u8 { 1 }
 
 fn default_rocksdb_log_level() -> String { "error".to_owned() }
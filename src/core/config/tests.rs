@@ -0,0 +1,93 @@
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+
+use ruma::server_name;
+use serde_json::json;
+
+use super::{check::check_default_power_levels, Config, Figment};
+
+fn template(value: serde_json::Value) -> BTreeMap<String, serde_json::Value> {
+	serde_json::from_value(value).expect("template is an object")
+}
+
+#[test]
+fn default_power_levels_valid() {
+	check_default_power_levels(&BTreeMap::new()).expect("empty template is valid");
+	check_default_power_levels(&template(json!({
+		"invite": 50,
+		"events": { "m.room.name": 50 },
+		"users": { "@admin:example.com": 100 },
+	})))
+	.expect("template is valid");
+}
+
+#[test]
+fn default_power_levels_invalid() {
+	for invalid in [
+		json!({ "invite": "everyone" }),
+		json!({ "events": 50 }),
+		json!({ "users": { "not a user id": 100 } }),
+	] {
+		check_default_power_levels(&template(invalid.clone()))
+			.expect_err(&format!("{invalid} was accepted"));
+	}
+}
+
+fn config(forbidden: &[&str], allowed: &[&str]) -> Config {
+	let raw_config = Figment::new()
+		.merge(("server_name", "example.com"))
+		.merge(("database_path", "/var/lib/conduwuit"))
+		.merge(("forbidden_remote_server_names", forbidden))
+		.merge(("allowed_remote_server_names", allowed));
+
+	Config::new(&raw_config).expect("minimal config")
+}
+
+#[test]
+fn federation_allowed_without_lists() {
+	let config = config(&[], &[]);
+
+	assert!(config.federation_allowed(server_name!("remote.example.org")));
+}
+
+#[test]
+fn federation_denied_server() {
+	let config = config(&["evil.example.org"], &[]);
+
+	assert!(!config.federation_allowed(server_name!("evil.example.org")));
+	assert!(config.federation_allowed(server_name!("remote.example.org")));
+}
+
+#[test]
+fn federation_allowlist() {
+	let config = config(&[], &["friend.example.org"]);
+
+	assert!(config.federation_allowed(server_name!("friend.example.org")));
+	assert!(!config.federation_allowed(server_name!("remote.example.org")));
+	// rooms on our own server stay reachable
+	assert!(config.federation_allowed(server_name!("example.com")));
+}
+
+#[test]
+fn federation_deny_overrides_allow() {
+	let config = config(&["friend.example.org"], &["friend.example.org"]);
+
+	assert!(!config.federation_allowed(server_name!("friend.example.org")));
+}
+
+#[test]
+fn retention_interval_zero_rejected() {
+	let raw_config = Figment::new()
+		.merge(("server_name", "example.com"))
+		.merge(("database_path", "/var/lib/conduwuit"));
+
+	let config = Config::new(&raw_config).expect("minimal config");
+	config.check().expect("default retention interval is valid");
+
+	let raw_config = raw_config.merge(("retention_interval_s", 0));
+	let config = Config::new(&raw_config).expect("minimal config");
+	config
+		.check()
+		.expect_err("zero retention interval was accepted");
+}
@@ -20,13 +20,7 @@ struct ExtractRedactedBecause {
 
 #[implement(super::Pdu)]
 pub fn redact(&mut self, room_version_id: &RoomVersionId, reason: &Self) -> Result {
-	self.unsigned = None;
-
-	let mut content = serde_json::from_str(self.content.get())
-		.map_err(|_| Error::bad_database("PDU in db has invalid content."))?;
-
-	redact_content_in_place(&mut content, room_version_id, self.kind.to_string())
-		.map_err(|e| Error::Redaction(self.sender.server_name().to_owned(), e))?;
+	self.redact_content(room_version_id)?;
 
 	self.unsigned = Some(
 		to_raw_value(&json!({
@@ -35,6 +29,21 @@ pub fn redact(&mut self, room_version_id: &RoomVersionId, reason: &Self) -> Resu
 		.expect("to string always works"),
 	);
 
+	Ok(())
+}
+
+/// Strips the content down to what the redaction algorithm keeps, without
+/// recording a redaction event in `unsigned`.
+#[implement(super::Pdu)]
+pub fn redact_content(&mut self, room_version_id: &RoomVersionId) -> Result {
+	self.unsigned = None;
+
+	let mut content = serde_json::from_str(self.content.get())
+		.map_err(|_| Error::bad_database("PDU in db has invalid content."))?;
+
+	redact_content_in_place(&mut content, room_version_id, self.kind.to_string())
+		.map_err(|e| Error::Redaction(self.sender.server_name().to_owned(), e))?;
+
 	self.content = to_raw_value(&content).expect("to string always works");
 
 	Ok(())
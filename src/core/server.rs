@@ -3,9 +3,11 @@ use std::{
 	time::SystemTime,
 };
 
+use http::StatusCode;
+use ruma::api::client::error::ErrorKind;
 use tokio::{runtime, sync::broadcast};
 
-use crate::{config::Config, err, log::Log, metrics::Metrics, Err, Result};
+use crate::{config::Config, err, log::Log, metrics::Metrics, Err, Error, Result};
 
 /// Server runtime state; public portion
 pub struct Server {
@@ -26,6 +28,11 @@ pub struct Server {
 	/// Restart desired; when true, restart it desired after shutdown.
 	pub restarting: AtomicBool,
 
+	/// Maintenance mode; when true, requests which would write to the database
+	/// are refused while reads continue to be served. Toggled at runtime by the
+	/// admin and not persisted across restarts.
+	pub read_only: AtomicBool,
+
 	/// Handle to the runtime
 	pub runtime: Option<runtime::Handle>,
 
@@ -48,6 +55,7 @@ impl Server {
 			stopping: AtomicBool::new(false),
 			reloading: AtomicBool::new(false),
 			restarting: AtomicBool::new(false),
+			read_only: AtomicBool::new(false),
 			runtime: runtime.clone(),
 			signal: broadcast::channel::<&'static str>(1).0,
 			log,
@@ -116,9 +124,67 @@ impl Server {
 			.ok_or_else(|| err!(debug_warn!("Server is shutting down.")))
 	}
 
+	#[inline]
+	pub fn check_writable(&self) -> Result {
+		if self.read_only.load(Ordering::Acquire) {
+			return Err(Error::Request(
+				ErrorKind::Unknown,
+				"Server is in read-only mode for maintenance.".into(),
+				StatusCode::SERVICE_UNAVAILABLE,
+			));
+		}
+
+		Ok(())
+	}
+
+	#[inline]
+	pub fn set_read_only(&self, read_only: bool) -> bool {
+		self.read_only.swap(read_only, Ordering::AcqRel)
+	}
+
 	#[inline]
 	pub fn running(&self) -> bool { !self.stopping.load(Ordering::Acquire) }
 
 	#[inline]
 	pub fn is_ours(&self, name: &str) -> bool { name == self.config.server_name }
 }
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use http::StatusCode;
+
+	use super::Server;
+	use crate::{
+		config::{Config, Figment},
+		log::{capture, Log, LogLevelReloadHandles},
+	};
+
+	fn server() -> Server {
+		let raw_config = Figment::new()
+			.merge(("server_name", "example.com"))
+			.merge(("database_path", "/var/lib/conduwuit"));
+		let config = Config::new(&raw_config).expect("minimal config");
+		let log = Log {
+			reload: LogLevelReloadHandles::default(),
+			capture: Arc::new(capture::State::new()),
+		};
+
+		Server::new(config, None, log)
+	}
+
+	#[test]
+	fn read_only_refuses_writes_and_keeps_serving_reads() {
+		let server = server();
+		server.check_writable().expect("writable by default");
+
+		assert!(!server.set_read_only(true));
+		let error = server.check_writable().expect_err("write refused");
+		assert_eq!(error.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+		server.check_running().expect("reads still served");
+
+		assert!(server.set_read_only(false));
+		server.check_writable().expect("writable again");
+	}
+}
@@ -8,3 +8,7 @@ pub fn verify_password(password: &str, password_hash: &str) -> Result {
 }
 
 pub fn password(password: &str) -> Result<String> { argon::password(password) }
+
+pub fn check_password_hash(password_hash: &str) -> Result {
+	argon::check_password_hash(password_hash)
+}
@@ -44,6 +44,16 @@ pub(super) fn verify_password(password: &str, password_hash: &str) -> Result<()>
 		.map_err(map_err)
 }
 
+/// Checks that `password_hash` is an Argon2 hash which
+/// [`verify_password`] can check passwords against.
+pub(super) fn check_password_hash(password_hash: &str) -> Result<()> {
+	let password_hash = PasswordHash::new(password_hash).map_err(map_err)?;
+	Algorithm::try_from(password_hash.algorithm).map_err(map_err)?;
+	Params::try_from(&password_hash).map_err(map_err)?;
+
+	Ok(())
+}
+
 fn map_err(e: password_hash::Error) -> Error { err!("{e}") }
 
 #[cfg(test)]
@@ -56,6 +66,41 @@ mod tests {
 		hash::verify_password(preimage, &digest).expect("verified");
 	}
 
+	#[test]
+	fn password_hash_check() {
+		use crate::utils::hash;
+		let digest = hash::password("temp123").expect("digest");
+		hash::check_password_hash(&digest).expect("well-formed");
+		hash::check_password_hash("temp123").expect_err("not a hash");
+		hash::check_password_hash("").expect_err("empty");
+	}
+
+	#[test]
+	fn password_hash_check_argon2_variants() {
+		use crate::utils::hash;
+		hash::check_password_hash(
+			"$argon2i$v=19$m=65536,t=2,p=1$c29tZXNhbHQ$wWKIMhR9lyDFvRz9YTZweHKfbftvj+qf+YFY4NeBbtA",
+		)
+		.expect("argon2i");
+		hash::check_password_hash(
+			"$argon2id$v=19$m=1,t=2,p=1$c29tZXNhbHQ$wWKIMhR9lyDFvRz9YTZweHKfbftvj+qf+YFY4NeBbtA",
+		)
+		.expect_err("memory cost below the minimum");
+	}
+
+	#[test]
+	fn password_hash_check_other_algorithms() {
+		use crate::utils::hash;
+		hash::check_password_hash(
+			"$pbkdf2-sha256$i=600000$c29tZXNhbHQ$ZXhhbXBsZWhhc2hleGFtcGxlaGFzaGV4YW1wbGU",
+		)
+		.expect_err("pbkdf2");
+		hash::check_password_hash("$scrypt$ln=16,r=8,p=1$c29tZXNhbHQ$ZXhhbXBsZWhhc2hleGFtcGxl")
+			.expect_err("scrypt");
+		hash::check_password_hash("$2b$12$GhvMmNVjRW29ulnudl.LbuAnUtN/LRfe1JsBm1Xu6LE3059z5Tr8m")
+			.expect_err("bcrypt");
+	}
+
 	#[test]
 	#[should_panic(expected = "unverified")]
 	fn password_hash_and_verify_fail() {
@@ -43,12 +43,15 @@ pub const MAPS: &[&str] = &[
 	"publicroomids",
 	"readreceiptid_readreceipt",
 	"referencedevents",
+	"reportid_report",
+	"roomid_expiredcount",
 	"roomid_invitedcount",
 	"roomid_inviteviaservers",
 	"roomid_joinedcount",
 	"roomid_pduleaves",
 	"roomid_shortroomid",
 	"roomid_shortstatehash",
+	"roomid_ts_eventid",
 	"roomserverids",
 	"roomsynctoken_shortstatehash",
 	"roomuserdataid_accountdata",
@@ -64,6 +67,7 @@ pub const MAPS: &[&str] = &[
 	"servercurrentevent_data",
 	"servername_educount",
 	"servernameevent_data",
+	"servernametxnid_response",
 	"serverroomids",
 	"shorteventid_authchain",
 	"shorteventid_eventid",
@@ -79,6 +83,7 @@ pub const MAPS: &[&str] = &[
 	"token_userdeviceid",
 	"tokenids",
 	"url_previews",
+	"userdeviceid_fallbackkey",
 	"userdeviceid_metadata",
 	"userdeviceid_token",
 	"userdevicesessionid_uiaainfo",
@@ -86,6 +91,7 @@ pub const MAPS: &[&str] = &[
 	"userfilterid_filter",
 	"userid_avatarurl",
 	"userid_blurhash",
+	"userid_dehydrateddevice",
 	"userid_devicelistversion",
 	"userid_displayname",
 	"userid_lastonetimekeyupdate",
@@ -96,6 +102,7 @@ pub const MAPS: &[&str] = &[
 	"userid_usersigningkeyid",
 	"useridprofilekey_value",
 	"openidtoken_expiresatuserid",
+	"userroomid_created",
 	"userroomid_highlightcount",
 	"userroomid_invitestate",
 	"userroomid_joined",
@@ -5,10 +5,13 @@ use std::fmt::Debug;
 
 use arrayvec::ArrayVec;
 use conduwuit::ruma::{serde::Raw, RoomId, UserId};
+use rocksdb::{DBCommon, Options};
 use serde::Serialize;
 
 use crate::{
-	de, ser,
+	de,
+	engine::Db,
+	ser,
 	ser::{serialize_to_vec, Json},
 	Ignore, Interfix,
 };
@@ -36,6 +39,61 @@ fn ser_tuple() {
 	assert_eq!(a, b);
 }
 
+#[test]
+fn ser_tuple_option_none() {
+	let user_id: &UserId = "@user:example.com".try_into().unwrap();
+	let txn_id = "txn0";
+
+	let mut a = user_id.as_bytes().to_vec();
+	a.push(0xFF);
+	a.push(0xFF);
+	a.extend_from_slice(txn_id.as_bytes());
+
+	let b = (user_id, None::<&str>, txn_id);
+	let b = serialize_to_vec(&b).expect("failed to serialize tuple");
+
+	assert_eq!(a, b);
+}
+
+#[test]
+fn ser_tuple_quoted_string() {
+	let user_id: &UserId = "@user:example.com".try_into().unwrap();
+	let device_id = "DEVICE";
+	let key_id = r#""signed_curve25519:AAAAAQ""#;
+
+	let mut a = user_id.as_bytes().to_vec();
+	a.push(0xFF);
+	a.extend_from_slice(device_id.as_bytes());
+	a.push(0xFF);
+	a.extend_from_slice(key_id.as_bytes());
+
+	let b = (user_id, device_id, key_id);
+	let b = serialize_to_vec(&b).expect("failed to serialize tuple");
+
+	assert_eq!(a, b);
+}
+
+#[test]
+fn ser_tuple_prefix_isolation() {
+	let room_id: &RoomId = "!room:example.com".try_into().unwrap();
+	let user_a: &UserId = "@user:example.com".try_into().unwrap();
+	let user_b: &UserId = "@user:example.com.evil".try_into().unwrap();
+	let kind = "com.example.\u{FFFF}\u{10FFFF}";
+
+	let prefix = (room_id, user_a, Interfix);
+	let prefix = serialize_to_vec(&prefix).expect("failed to serialize prefix");
+
+	let a = (room_id, user_a, kind);
+	let a = serialize_to_vec(&a).expect("failed to serialize tuple");
+
+	let b = (room_id, user_b, kind);
+	let b = serialize_to_vec(&b).expect("failed to serialize tuple");
+
+	assert!(a.starts_with(&prefix), "key not found under its own prefix");
+	assert!(!b.starts_with(&prefix), "key of another user matched the prefix");
+	assert_eq!(a.iter().filter(|&&byte| byte == 0xFF).count(), 2, "string embedded a separator");
+}
+
 #[test]
 #[should_panic(expected = "I/O error: failed to write whole buffer")]
 fn ser_overflow() {
@@ -290,3 +348,31 @@ fn ser_array() {
 	let s = serialize_to_vec(arr).expect("failed to serialize");
 	assert_eq!(&s, &v, "serialization does not match");
 }
+
+#[test]
+fn synced_wal_survives_unclean_stop() {
+	let path = std::env::temp_dir().join(format!("conduwuit-wal-{}", std::process::id()));
+
+	// as in db_options(), writes stay in memory until the WAL is flushed
+	let mut opts = Options::default();
+	opts.create_if_missing(true);
+	opts.set_manual_wal_flush(true);
+
+	let db = Db::open(&opts, &path).expect("failed to open database");
+	db.put(b"counter", 42_u64.to_be_bytes())
+		.expect("failed to write");
+
+	// what Engine::sync() does at shutdown
+	DBCommon::flush_wal(&db, true).expect("failed to sync");
+
+	// the primary is never closed; recovering from the WAL alone must find the
+	// write, as after the process is killed
+	let recovered =
+		Db::open_for_read_only(&opts, &path, false).expect("failed to reopen database");
+	let counter = recovered.get(b"counter").expect("failed to read");
+	assert_eq!(counter.as_deref(), Some(&42_u64.to_be_bytes()[..]));
+
+	drop(recovered);
+	drop(db);
+	Db::destroy(&opts, &path).expect("failed to remove database");
+}
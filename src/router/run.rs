@@ -97,6 +97,12 @@ pub(crate) async fn stop(services: Arc<Services>) -> Result<()> {
 	// with Services construction it can't be done in services.stop().
 	if let Some(db) = db.upgrade() {
 		db.db.shutdown_pool().await;
+
+		// Sync the write-ahead-log so the last writes are durable even if the
+		// process is killed before the database finishes closing.
+		if let Err(e) = db.db.sync() {
+			error!("Failed to sync database during shutdown: {e}");
+		}
 	}
 
 	#[cfg(feature = "systemd")]
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use conduwuit::{
 	err, implement,
 	utils::{result::LogErr, stream::TryIgnore, ReadyExt},
-	Err, Result,
+	Err, Result, Server,
 };
 use database::{Deserialized, Handle, Interfix, Json, Map};
 use futures::{Stream, StreamExt, TryFutureExt};
@@ -30,6 +30,7 @@ struct Data {
 }
 
 struct Services {
+	server: Arc<Server>,
 	globals: Dep<globals::Service>,
 }
 
@@ -37,6 +38,7 @@ impl crate::Service for Service {
 	fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
 		Ok(Arc::new(Self {
 			services: Services {
+				server: args.server.clone(),
 				globals: args.depend::<globals::Service>("globals"),
 			},
 			db: Data {
@@ -60,6 +62,8 @@ pub async fn update(
 	event_type: RoomAccountDataEventType,
 	data: &serde_json::Value,
 ) -> Result<()> {
+	self.services.server.check_writable()?;
+
 	if data.get("type").is_none() || data.get("content").is_none() {
 		return Err!(Request(InvalidParam("Account data doesn't have all required fields.")));
 	}
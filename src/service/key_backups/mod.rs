@@ -1,3 +1,5 @@
+mod tests;
+
 use std::{collections::BTreeMap, sync::Arc};
 
 use conduwuit::{
@@ -158,6 +160,15 @@ pub async fn add_key(
 		return Err!(Request(NotFound("Tried to update nonexistent backup.")));
 	}
 
+	if let Ok(existing) = self
+		.get_session(user_id, version, room_id, session_id)
+		.await
+	{
+		if !should_replace_key(&existing, key_data) {
+			return Ok(());
+		}
+	}
+
 	let count = self.services.globals.next_count().unwrap();
 	self.db.backupid_etag.put(key, count);
 
@@ -169,6 +180,22 @@ pub async fn add_key(
 	Ok(())
 }
 
+/// Whether an uploaded session key should replace the one already backed up,
+/// following the spec's precedence: verified keys win, then the lower
+/// `first_message_index`, then the lower `forwarded_count`.
+fn should_replace_key(existing: &Raw<KeyBackupData>, new: &Raw<KeyBackupData>) -> bool {
+	let (Ok(existing), Ok(new)) = (existing.deserialize(), new.deserialize()) else {
+		return true;
+	};
+
+	if existing.is_verified != new.is_verified {
+		return new.is_verified;
+	}
+
+	(new.first_message_index, new.forwarded_count)
+		< (existing.first_message_index, existing.forwarded_count)
+}
+
 #[implement(Service)]
 pub async fn count_keys(&self, user_id: &UserId, version: &str) -> usize {
 	let prefix = (user_id, version);
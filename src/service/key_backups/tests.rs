@@ -0,0 +1,40 @@
+#![cfg(test)]
+
+use ruma::{api::client::backup::KeyBackupData, serde::Raw};
+use serde_json::{json, value::to_raw_value};
+
+use super::should_replace_key;
+
+fn key(first_message_index: u64, forwarded_count: u64, is_verified: bool) -> Raw<KeyBackupData> {
+	let value = json!({
+		"first_message_index": first_message_index,
+		"forwarded_count": forwarded_count,
+		"is_verified": is_verified,
+		"session_data": {
+			"ephemeral": "aGVsbG8",
+			"ciphertext": "aGVsbG8",
+			"mac": "aGVsbG8",
+		},
+	});
+
+	Raw::from_json(to_raw_value(&value).expect("valid json"))
+}
+
+#[test]
+fn verified_key_is_kept() {
+	assert!(!should_replace_key(&key(5, 0, true), &key(0, 0, false)));
+	assert!(should_replace_key(&key(0, 0, false), &key(5, 0, true)));
+}
+
+#[test]
+fn lower_first_message_index_wins() {
+	assert!(should_replace_key(&key(5, 0, true), &key(2, 3, true)));
+	assert!(!should_replace_key(&key(2, 0, true), &key(5, 0, true)));
+}
+
+#[test]
+fn lower_forwarded_count_breaks_ties() {
+	assert!(should_replace_key(&key(2, 3, false), &key(2, 1, false)));
+	assert!(!should_replace_key(&key(2, 1, false), &key(2, 3, false)));
+	assert!(!should_replace_key(&key(2, 1, false), &key(2, 1, false)));
+}
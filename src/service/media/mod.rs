@@ -103,6 +103,24 @@ impl Service {
 		Ok(())
 	}
 
+	/// Checks the content type of a local upload against the configured allow
+	/// and deny lists.
+	pub fn check_upload_content_type(&self, content_type: Option<&str>) -> Result {
+		let config = &self.services.server.config;
+		if !content_type_allowed(
+			content_type,
+			&config.allowed_media_upload_content_types,
+			&config.forbidden_media_upload_content_types,
+		) {
+			let content_type = content_type.unwrap_or("none");
+			return Err!(Request(Forbidden(
+				"Uploading media of content type {content_type} is not allowed."
+			)));
+		}
+
+		Ok(())
+	}
+
 	/// Deletes a file in the database and from the media directory via an MXC
 	pub async fn delete(&self, mxc: &Mxc<'_>) -> Result<()> {
 		if let Ok(keys) = self.db.search_mxc_metadata_prefix(mxc).await {
@@ -427,3 +445,31 @@ impl Service {
 #[inline]
 #[must_use]
 pub fn encode_key(key: &[u8]) -> String { general_purpose::URL_SAFE_NO_PAD.encode(key) }
+
+/// Whether `content_type` passes the allow and deny lists. Parameters such as
+/// `charset` are ignored and types compare case-insensitively. Uploads without
+/// a content type are only refused by a non-empty allow list.
+fn content_type_allowed(
+	content_type: Option<&str>,
+	allowed: &[String],
+	forbidden: &[String],
+) -> bool {
+	let Some(essence) = content_type
+		.and_then(|ct| ct.split(';').next())
+		.map(str::trim)
+	else {
+		return allowed.is_empty();
+	};
+
+	let matches = |pattern: &String| content_type_matches(pattern.trim(), essence);
+	!forbidden.iter().any(matches) && (allowed.is_empty() || allowed.iter().any(matches))
+}
+
+fn content_type_matches(pattern: &str, essence: &str) -> bool {
+	match pattern.strip_suffix("/*") {
+		| Some(kind) => essence
+			.split_once('/')
+			.is_some_and(|(essence_kind, _)| essence_kind.eq_ignore_ascii_case(kind)),
+		| None => pattern.eq_ignore_ascii_case(essence),
+	}
+}
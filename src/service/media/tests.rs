@@ -103,3 +103,21 @@ async fn long_file_names_works() {
 		r.to_str().unwrap().len()
 	);
 }
+
+#[test]
+fn upload_content_type_lists() {
+	use super::content_type_allowed;
+
+	let allowed = vec!["image/*".to_owned(), "text/plain".to_owned()];
+	let forbidden = vec!["application/x-msdownload".to_owned(), "image/svg+xml".to_owned()];
+
+	assert!(content_type_allowed(Some("image/png"), &allowed, &forbidden));
+	assert!(content_type_allowed(Some("Text/Plain; charset=utf-8"), &allowed, &forbidden));
+	assert!(!content_type_allowed(Some("image/svg+xml"), &allowed, &forbidden));
+	assert!(!content_type_allowed(Some("application/pdf"), &allowed, &forbidden));
+	assert!(!content_type_allowed(None, &allowed, &forbidden));
+
+	assert!(content_type_allowed(Some("application/pdf"), &[], &forbidden));
+	assert!(!content_type_allowed(Some("application/x-msdownload"), &[], &forbidden));
+	assert!(content_type_allowed(None, &[], &forbidden));
+}
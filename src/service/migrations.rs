@@ -14,10 +14,12 @@ use itertools::Itertools;
 use ruma::{
 	events::{
 		push_rules::PushRulesEvent, room::member::MembershipState, GlobalAccountDataEventType,
+		StateEventType,
 	},
 	push::Ruleset,
-	OwnedUserId, RoomId, UserId,
+	OwnedEventId, OwnedUserId, RoomId, UInt, UserId,
 };
+use serde::Deserialize;
 
 use crate::{media, Services};
 
@@ -73,6 +75,8 @@ async fn fresh(services: &Services) -> Result<()> {
 	db["global"].insert(b"retroactively_fix_bad_data_from_roomuserid_joined", []);
 	db["global"].insert(b"fix_referencedevents_missing_sep", []);
 	db["global"].insert(b"fix_readreceiptid_readreceipt_duplicates", []);
+	db["global"].insert(b"populate_userroomid_created", []);
+	db["global"].insert(b"populate_roomid_ts_eventid", []);
 
 	// Create the admin room and server user on first run
 	crate::admin::create_admin_room(services).boxed().await?;
@@ -142,6 +146,22 @@ async fn migrate(services: &Services) -> Result<()> {
 		fix_readreceiptid_readreceipt_duplicates(services).await?;
 	}
 
+	if db["global"]
+		.get(b"populate_userroomid_created")
+		.await
+		.is_not_found()
+	{
+		populate_userroomid_created(services).await?;
+	}
+
+	if db["global"]
+		.get(b"populate_roomid_ts_eventid")
+		.await
+		.is_not_found()
+	{
+		populate_roomid_ts_eventid(services).await?;
+	}
+
 	let version_match = services.globals.db.database_version().await == DATABASE_VERSION
 		|| services.globals.db.database_version().await == CONDUIT_DATABASE_VERSION;
 
@@ -563,3 +583,77 @@ async fn fix_readreceiptid_readreceipt_duplicates(services: &Services) -> Result
 	db["global"].insert(b"fix_readreceiptid_readreceipt_duplicates", []);
 	db.db.cleanup()
 }
+
+async fn populate_userroomid_created(services: &Services) -> Result {
+	warn!("Indexing rooms created by local users...");
+
+	let db = &services.db;
+	let cork = db.cork_and_sync();
+
+	let total = services
+		.rooms
+		.metadata
+		.iter_ids()
+		.filter_map(|room_id| async move {
+			services
+				.rooms
+				.state_accessor
+				.room_state_get(room_id, &StateEventType::RoomCreate, "")
+				.await
+				.ok()
+				.filter(|create| services.globals.user_is_local(&create.sender))
+		})
+		.ready_fold(0_usize, |total, create| {
+			services
+				.rooms
+				.state_cache
+				.mark_as_created(&create.sender, &create.room_id);
+
+			total.saturating_add(1)
+		})
+		.await;
+
+	drop(cork);
+	info!(?total, "Indexed rooms created by local users in 'userroomid_created'.");
+
+	db["global"].insert(b"populate_userroomid_created", []);
+	db.db.cleanup()
+}
+
+async fn populate_roomid_ts_eventid(services: &Services) -> Result {
+	#[derive(Deserialize)]
+	struct ExtractTimestamp {
+		event_id: OwnedEventId,
+		origin_server_ts: UInt,
+	}
+
+	warn!("Indexing timeline events by timestamp...");
+
+	let db = &services.db;
+	let cork = db.cork_and_sync();
+
+	let total = db["pduid_pdu"]
+		.raw_stream()
+		.expect_ok()
+		.ready_fold(0_usize, |total, (pdu_id, pdu)| {
+			let Ok(pdu) = serde_json::from_slice::<ExtractTimestamp>(pdu) else {
+				debug_warn!(?pdu_id, "Skipping invalid PDU");
+				return total;
+			};
+
+			services.rooms.timeline.index_timestamp(
+				&pdu_id.into(),
+				&pdu.event_id,
+				pdu.origin_server_ts.into(),
+			);
+
+			total.saturating_add(1)
+		})
+		.await;
+
+	drop(cork);
+	info!(?total, "Indexed timeline events in 'roomid_ts_eventid'.");
+
+	db["global"].insert(b"populate_roomid_ts_eventid", []);
+	db.db.cleanup()
+}
@@ -15,6 +15,7 @@ pub mod key_backups;
 pub mod media;
 pub mod presence;
 pub mod pusher;
+pub mod reports;
 pub mod resolver;
 pub mod rooms;
 pub mod sending;
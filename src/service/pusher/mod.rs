@@ -1,4 +1,11 @@
-use std::{fmt::Debug, mem, sync::Arc};
+mod tests;
+
+use std::{
+	collections::HashMap,
+	fmt::Debug,
+	mem,
+	sync::{Arc, Mutex},
+};
 
 use bytes::BytesMut;
 use conduwuit::{
@@ -26,7 +33,7 @@ use ruma::{
 		Action, PushConditionPowerLevelsCtx, PushConditionRoomCtx, PushFormat, Ruleset, Tweak,
 	},
 	serde::Raw,
-	uint, RoomId, UInt, UserId,
+	uint, OwnedUserId, RoomId, UInt, UserId,
 };
 
 use crate::{client, globals, rooms, sending, users, Dep};
@@ -34,8 +41,12 @@ use crate::{client, globals, rooms, sending, users, Dep};
 pub struct Service {
 	db: Data,
 	services: Services,
+	failures: Mutex<HashMap<(OwnedUserId, String), u32>>,
 }
 
+/// Consecutive failed deliveries after which a pusher is removed.
+const MAX_PUSH_FAILURES: u32 = 10;
+
 struct Services {
 	globals: Dep<globals::Service>,
 	client: Dep<client::Service>,
@@ -64,6 +75,7 @@ impl crate::Service for Service {
 				users: args.depend::<users::Service>("users"),
 				sending: args.depend::<sending::Service>("sending"),
 			},
+			failures: Mutex::new(HashMap::new()),
 		}))
 	}
 
@@ -126,20 +138,44 @@ impl Service {
 				self.db.senderkey_pusher.put(key, Json(pusher));
 			},
 			| set_pusher::v3::PusherAction::Delete(ids) => {
-				let key = (sender, ids.pushkey.as_str());
-				self.db.senderkey_pusher.del(key);
-
-				self.services
-					.sending
-					.cleanup_events(None, Some(sender), Some(ids.pushkey.as_str()))
-					.await
-					.ok();
+				self.delete_pusher(sender, ids.pushkey.as_str()).await;
 			},
 		}
 
 		Ok(())
 	}
 
+	/// Removes a pusher along with any notifications still queued for it.
+	pub async fn delete_pusher(&self, sender: &UserId, pushkey: &str) {
+		let key = (sender, pushkey);
+		self.db.senderkey_pusher.del(key);
+		self.failures
+			.lock()
+			.expect("locked")
+			.remove(&(sender.to_owned(), pushkey.to_owned()));
+
+		self.services
+			.sending
+			.cleanup_events(None, Some(sender), Some(pushkey))
+			.await
+			.ok();
+	}
+
+	/// Records whether delivery to a pusher failed, returning its number of
+	/// consecutive failures.
+	fn count_failure(&self, sender: &UserId, pushkey: &str, failed: bool) -> u32 {
+		let key = (sender.to_owned(), pushkey.to_owned());
+		let mut failures = self.failures.lock().expect("locked");
+		if !failed {
+			failures.remove(&key);
+			return 0;
+		}
+
+		let count = failures.entry(key).or_default();
+		*count = count.saturating_add(1);
+		*count
+	}
+
 	pub async fn get_pusher(&self, sender: &UserId, pushkey: &str) -> Result<Pusher> {
 		let senderkey = (sender, pushkey);
 		self.db
@@ -304,7 +340,7 @@ impl Service {
 		}
 
 		if notify == Some(true) {
-			self.send_notice(unread, pusher, tweaks, pdu).await?;
+			self.send_notice(user, unread, pusher, tweaks, pdu).await?;
 		}
 		// Else the event triggered no actions
 
@@ -356,6 +392,7 @@ impl Service {
 	#[tracing::instrument(skip(self, unread, pusher, tweaks, event))]
 	async fn send_notice(
 		&self,
+		user: &UserId,
 		unread: UInt,
 		pusher: &Pusher,
 		tweaks: Vec<Tweak>,
@@ -420,13 +457,7 @@ impl Service {
 					notifi.counts = NotificationCounts::default();
 				}
 
-				if event_id_only {
-					self.send_request(
-						&http.url,
-						send_event_notification::v1::Request::new(notifi),
-					)
-					.await?;
-				} else {
+				if !event_id_only {
 					if event.kind == TimelineEventType::RoomEncrypted
 						|| tweaks
 							.iter()
@@ -461,15 +492,20 @@ impl Service {
 						.get_canonical_alias(&event.room_id)
 						.await
 						.ok();
+				}
 
-					self.send_request(
-						&http.url,
-						send_event_notification::v1::Request::new(notifi),
-					)
-					.await?;
+				let response = self
+					.send_request(&http.url, send_event_notification::v1::Request::new(notifi))
+					.await;
+
+				let pushkey = &pusher.ids.pushkey;
+				let failures = self.count_failure(user, pushkey, response.is_err());
+				if pusher_is_dead(pushkey, &response, failures) {
+					warn!(%user, %pushkey, failures, "Push gateway rejects pushkey, removing pusher");
+					self.delete_pusher(user, pushkey).await;
 				}
 
-				Ok(())
+				response.map(|_| ())
 			},
 			// TODO: Handle email
 			//PusherKind::Email(_) => Ok(()),
@@ -477,3 +513,17 @@ impl Service {
 		}
 	}
 }
+
+/// Whether a pusher should be removed after its gateway answered a
+/// notification with `response`, having now failed `failures` times in a row.
+/// Pushkeys the gateway lists as rejected are removed right away.
+fn pusher_is_dead(
+	pushkey: &str,
+	response: &Result<send_event_notification::v1::Response>,
+	failures: u32,
+) -> bool {
+	match response {
+		| Ok(response) => response.rejected.iter().any(|rejected| rejected == pushkey),
+		| Err(_) => failures >= MAX_PUSH_FAILURES,
+	}
+}
@@ -0,0 +1,41 @@
+#![cfg(test)]
+
+use conduwuit::{err, Result};
+use ruma::api::{push_gateway::send_event_notification::v1::Response, IncomingResponse};
+
+use super::{pusher_is_dead, MAX_PUSH_FAILURES};
+
+/// Parses a push gateway's answer to a notification as send_request() does.
+fn gateway_response(body: &str) -> Result<Response> {
+	let response = http::Response::builder()
+		.status(200)
+		.body(body.as_bytes().to_vec())
+		.expect("valid response");
+
+	Response::try_from_http_response(response).map_err(|e| err!(BadServerResponse("{e}")))
+}
+
+#[test]
+fn rejected_pushkey_removes_pusher() {
+	let response = gateway_response(r#"{ "rejected": ["pushkey"] }"#);
+
+	assert!(pusher_is_dead("pushkey", &response, 0));
+}
+
+#[test]
+fn other_rejected_pushkey_keeps_pusher() {
+	let response = gateway_response(r#"{ "rejected": ["other"] }"#);
+	assert!(!pusher_is_dead("pushkey", &response, 0));
+
+	let response = gateway_response(r#"{ "rejected": [] }"#);
+	assert!(!pusher_is_dead("pushkey", &response, 0));
+}
+
+#[test]
+fn repeated_failures_remove_pusher() {
+	let failed: Result<Response> = Err(err!(BadServerResponse("gateway unreachable")));
+
+	assert!(!pusher_is_dead("pushkey", &failed, 1));
+	assert!(!pusher_is_dead("pushkey", &failed, MAX_PUSH_FAILURES.saturating_sub(1)));
+	assert!(pusher_is_dead("pushkey", &failed, MAX_PUSH_FAILURES));
+}
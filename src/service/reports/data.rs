@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use conduwuit::{utils::stream::TryIgnore, Result};
+use database::{Json, Map};
+use futures::{Stream, StreamExt};
+use ruma::{
+	EventId, Int, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedUserId, RoomId,
+	UserId,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{globals, Dep};
+
+pub struct Data {
+	reportid_report: Arc<Map>,
+	services: Services,
+}
+
+struct Services {
+	globals: Dep<globals::Service>,
+}
+
+/// An event report as filed by a user.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Report {
+	pub reporter: OwnedUserId,
+	pub room_id: OwnedRoomId,
+	pub event_id: OwnedEventId,
+	pub score: Option<Int>,
+	pub reason: Option<String>,
+	pub ts: MilliSecondsSinceUnixEpoch,
+}
+
+impl Data {
+	pub(super) fn new(args: &crate::Args<'_>) -> Self {
+		Self {
+			reportid_report: args.db["reportid_report"].clone(),
+			services: Services {
+				globals: args.depend::<globals::Service>("globals"),
+			},
+		}
+	}
+
+	/// Stores a report and returns its id. Ids increase in the order reports
+	/// are filed.
+	pub fn report_event(
+		&self,
+		reporter: &UserId,
+		room_id: &RoomId,
+		event_id: &EventId,
+		score: Option<Int>,
+		reason: Option<&str>,
+	) -> Result<u64> {
+		let report = Report {
+			reporter: reporter.to_owned(),
+			room_id: room_id.to_owned(),
+			event_id: event_id.to_owned(),
+			score,
+			reason: reason.map(ToOwned::to_owned),
+			ts: MilliSecondsSinceUnixEpoch::now(),
+		};
+
+		let id = self.services.globals.next_count()?;
+		self.reportid_report.put(id, Json(report));
+
+		Ok(id)
+	}
+
+	/// Reports in the order they were filed, starting after the report with
+	/// id `since`.
+	pub fn reports_list(
+		&self,
+		limit: usize,
+		since: Option<u64>,
+	) -> impl Stream<Item = (u64, Report)> + Send + '_ {
+		self.reportid_report
+			.stream_from::<u64, Report, _>(&first_report_id(since))
+			.ignore_err()
+			.take(limit)
+	}
+}
+
+/// The lowest report id to list when resuming after `since`.
+pub(super) fn first_report_id(since: Option<u64>) -> u64 {
+	since.map_or(0, |since| since.saturating_add(1))
+}
@@ -0,0 +1,21 @@
+mod data;
+mod tests;
+
+use std::sync::Arc;
+
+use conduwuit::Result;
+
+pub use self::data::{Data, Report};
+
+/// Event reports filed by users, kept for admins to review.
+pub struct Service {
+	pub db: Data,
+}
+
+impl crate::Service for Service {
+	fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
+		Ok(Arc::new(Self { db: Data::new(&args) }))
+	}
+
+	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
+}
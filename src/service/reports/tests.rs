@@ -0,0 +1,45 @@
+#![cfg(test)]
+
+use database::{serialize_key, serialize_val, Json};
+use ruma::{int, owned_event_id, owned_room_id, owned_user_id, MilliSecondsSinceUnixEpoch, UInt};
+
+use super::{data::first_report_id, Report};
+
+#[test]
+fn filed_report_round_trip() {
+	let report = Report {
+		reporter: owned_user_id!("@alice:example.com"),
+		room_id: owned_room_id!("!room:example.com"),
+		event_id: owned_event_id!("$spam:example.org"),
+		score: Some(int!(-100)),
+		reason: Some("spam".to_owned()),
+		ts: MilliSecondsSinceUnixEpoch(UInt::from(1_700_000_000_000_u32)),
+	};
+
+	let stored = serialize_val(Json(&report)).expect("serializes");
+	let listed: Report = serde_json::from_slice(&stored).expect("deserializes");
+
+	assert_eq!(listed.reporter, report.reporter);
+	assert_eq!(listed.room_id, report.room_id);
+	assert_eq!(listed.event_id, report.event_id);
+	assert_eq!(listed.score, report.score);
+	assert_eq!(listed.reason, report.reason);
+	assert_eq!(listed.ts, report.ts);
+}
+
+#[test]
+fn reports_listed_in_filing_order() {
+	let ids = [1_u64, 255, 256, 70_000];
+	let keys: Vec<_> = ids
+		.iter()
+		.map(|id| serialize_key(id).expect("serializes").to_vec())
+		.collect();
+
+	assert!(keys.is_sorted());
+}
+
+#[test]
+fn reports_list_resumes_after_since() {
+	assert_eq!(first_report_id(None), 0);
+	assert_eq!(first_report_id(Some(41)), 42);
+}
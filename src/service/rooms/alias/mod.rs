@@ -1,9 +1,11 @@
 mod remote;
+mod tests;
 
 use std::sync::Arc;
 
 use conduwuit::{
 	err,
+	pdu::PduBuilder,
 	utils::{stream::TryIgnore, ReadyExt},
 	Err, Error, Result,
 };
@@ -12,10 +14,14 @@ use futures::{Stream, StreamExt};
 use ruma::{
 	api::client::error::ErrorKind,
 	events::{
-		room::power_levels::{RoomPowerLevels, RoomPowerLevelsEventContent},
+		room::{
+			canonical_alias::RoomCanonicalAliasEventContent,
+			power_levels::{RoomPowerLevels, RoomPowerLevelsEventContent},
+		},
 		StateEventType,
 	},
-	OwnedRoomId, OwnedServerName, OwnedUserId, RoomAliasId, RoomId, RoomOrAliasId, UserId,
+	EventId, OwnedRoomAliasId, OwnedRoomId, OwnedServerName, OwnedUserId, RoomAliasId, RoomId,
+	RoomOrAliasId, UserId,
 };
 
 use crate::{admin, appservice, appservice::RegistrationInfo, globals, rooms, sending, Dep};
@@ -36,7 +42,9 @@ struct Services {
 	appservice: Dep<appservice::Service>,
 	globals: Dep<globals::Service>,
 	sending: Dep<sending::Service>,
+	state: Dep<rooms::state::Service>,
 	state_accessor: Dep<rooms::state_accessor::Service>,
+	timeline: Dep<rooms::timeline::Service>,
 }
 
 impl crate::Service for Service {
@@ -52,8 +60,10 @@ impl crate::Service for Service {
 				appservice: args.depend::<appservice::Service>("appservice"),
 				globals: args.depend::<globals::Service>("globals"),
 				sending: args.depend::<sending::Service>("sending"),
+				state: args.depend::<rooms::state::Service>("rooms::state"),
 				state_accessor: args
 					.depend::<rooms::state_accessor::Service>("rooms::state_accessor"),
+				timeline: args.depend::<rooms::timeline::Service>("rooms::timeline"),
 			},
 		}))
 	}
@@ -120,6 +130,60 @@ impl Service {
 		Ok(())
 	}
 
+	/// Sets the room's canonical alias and alt aliases by sending an
+	/// `m.room.canonical_alias` state event from `sender`.
+	#[tracing::instrument(skip(self))]
+	pub async fn set_canonical_alias(
+		&self,
+		room_id: &RoomId,
+		sender: &UserId,
+		alias: Option<&RoomAliasId>,
+		alt_aliases: Vec<OwnedRoomAliasId>,
+	) -> Result<Arc<EventId>> {
+		let content = RoomCanonicalAliasEventContent {
+			alias: alias.map(ToOwned::to_owned),
+			alt_aliases,
+		};
+
+		self.check_canonical_alias(room_id, &content).await?;
+		if !self.user_can_set_canonical_alias(room_id, sender).await? {
+			return Err!(Request(Forbidden("User is not permitted to set the canonical alias.")));
+		}
+
+		let state_lock = self.services.state.mutex.lock(room_id).await;
+		self.services
+			.timeline
+			.build_and_append_pdu(
+				PduBuilder::state(String::new(), &content),
+				sender,
+				room_id,
+				&state_lock,
+			)
+			.await
+	}
+
+	/// Checks that the alias and every alt alias of a canonical alias event
+	/// exist on this server and point to `room_id`. Aliases of other servers
+	/// are refused rather than resolved over federation.
+	pub async fn check_canonical_alias(
+		&self,
+		room_id: &RoomId,
+		content: &RoomCanonicalAliasEventContent,
+	) -> Result {
+		for alias in content.alias.iter().chain(&content.alt_aliases) {
+			let server_is_ours = self.services.globals.server_is_ours(alias.server_name());
+			let target = if server_is_ours {
+				self.resolve_local_alias(alias).await.ok()
+			} else {
+				None
+			};
+
+			check_alias_target(room_id, server_is_ours, target.as_deref())?;
+		}
+
+		Ok(())
+	}
+
 	#[inline]
 	pub async fn resolve(&self, room: &RoomOrAliasId) -> Result<OwnedRoomId> {
 		self.resolve_with_servers(room, None)
@@ -220,11 +284,19 @@ impl Service {
 		}
 
 		// Checking whether the user is able to change canonical aliases of the room
+		self.user_can_set_canonical_alias(&room_id, user_id).await
+	}
+
+	async fn user_can_set_canonical_alias(
+		&self,
+		room_id: &RoomId,
+		user_id: &UserId,
+	) -> Result<bool> {
 		if let Ok(content) = self
 			.services
 			.state_accessor
 			.room_state_get_content::<RoomPowerLevelsEventContent>(
-				&room_id,
+				room_id,
 				&StateEventType::RoomPowerLevels,
 				"",
 			)
@@ -239,7 +311,7 @@ impl Service {
 		if let Ok(event) = self
 			.services
 			.state_accessor
-			.room_state_get(&room_id, &StateEventType::RoomCreate, "")
+			.room_state_get(room_id, &StateEventType::RoomCreate, "")
 			.await
 		{
 			return Ok(event.sender == user_id);
@@ -319,3 +391,20 @@ impl Service {
 		Ok(())
 	}
 }
+
+/// Whether an alias of a canonical alias event may be set for `room_id`,
+/// given whether it is a local alias and the room it resolves to.
+fn check_alias_target(room_id: &RoomId, server_is_ours: bool, target: Option<&RoomId>) -> Result {
+	if !server_is_ours {
+		return Err!(Request(Forbidden("canonical_alias must be for this server")));
+	}
+
+	if target != Some(room_id) {
+		return Err!(Request(Forbidden(
+			"You are only allowed to send canonical_alias events when its aliases already \
+			 exist and point to this room"
+		)));
+	}
+
+	Ok(())
+}
@@ -0,0 +1,75 @@
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+
+use conduwuit::Result;
+use ruma::{
+	events::room::canonical_alias::RoomCanonicalAliasEventContent, room_alias_id, room_id,
+	server_name, OwnedRoomAliasId, RoomId,
+};
+use serde_json::{json, Value as JsonValue};
+
+use super::check_alias_target;
+
+/// Checks a canonical alias event for `!room:example.org` the way
+/// `Service::check_canonical_alias` does, against a local alias directory.
+fn check(content: JsonValue) -> Result {
+	let directory: BTreeMap<OwnedRoomAliasId, &RoomId> = [
+		(room_alias_id!("#main:example.org").to_owned(), room_id!("!room:example.org")),
+		(room_alias_id!("#alt:example.org").to_owned(), room_id!("!room:example.org")),
+		(room_alias_id!("#other:example.org").to_owned(), room_id!("!other:example.org")),
+	]
+	.into();
+
+	let content: RoomCanonicalAliasEventContent =
+		serde_json::from_value(content).expect("valid content");
+
+	for alias in content.alias.iter().chain(&content.alt_aliases) {
+		let server_is_ours = alias.server_name() == server_name!("example.org");
+		let target = directory.get(alias).copied();
+		check_alias_target(room_id!("!room:example.org"), server_is_ours, target)?;
+	}
+
+	Ok(())
+}
+
+#[test]
+fn canonical_alias_with_alt_aliases_accepted() {
+	let content = json!({
+		"alias": "#main:example.org",
+		"alt_aliases": ["#alt:example.org"],
+	});
+
+	check(content).expect("aliases point to the room");
+}
+
+#[test]
+fn canonical_alias_of_another_room_rejected() {
+	let content = json!({ "alias": "#other:example.org" });
+
+	check(content).expect_err("alias points to another room");
+}
+
+#[test]
+fn alt_alias_of_another_room_rejected() {
+	let content = json!({
+		"alias": "#main:example.org",
+		"alt_aliases": ["#alt:example.org", "#other:example.org"],
+	});
+
+	check(content).expect_err("alt alias points to another room");
+}
+
+#[test]
+fn unknown_alias_rejected() {
+	let content = json!({ "alias": "#missing:example.org" });
+
+	check(content).expect_err("alias does not exist");
+}
+
+#[test]
+fn remote_alias_rejected() {
+	let content = json!({ "alt_aliases": ["#main:remote.example.org"] });
+
+	check(content).expect_err("alias is not ours");
+}
@@ -0,0 +1,23 @@
+use conduwuit::{err, Err, Result};
+use ruma::{CanonicalJsonObject, CanonicalJsonValue};
+
+/// Checks that the `depth` of a PDU is no more than `max_gap` beyond
+/// `max_depth`, the greatest depth among the room's forward extremities.
+pub fn check_event_depth(pdu: &CanonicalJsonObject, max_depth: u64, max_gap: u64) -> Result {
+	let depth = pdu
+		.get("depth")
+		.and_then(|depth| match depth {
+			| CanonicalJsonValue::Integer(depth) => u64::try_from(i64::from(*depth)).ok(),
+			| _ => None,
+		})
+		.ok_or_else(|| err!(Request(InvalidParam("PDU does not have a valid depth"))))?;
+
+	if depth > max_depth.saturating_add(max_gap) {
+		return Err!(Request(InvalidParam(
+			"PDU depth {depth} is more than {max_gap} beyond the room's current depth \
+			 ({max_depth})"
+		)));
+	}
+
+	Ok(())
+}
@@ -0,0 +1,42 @@
+use conduwuit::{err, Err, Result};
+use ruma::{CanonicalJsonObject, CanonicalJsonValue, ServerName, UserId};
+
+/// Checks that a PDU received from `origin` was created by one of its users,
+/// and that its `origin` field, when present, agrees. Membership events
+/// completing a third-party invite are exempt from the sender check.
+pub fn check_event_origin(origin: &ServerName, pdu: &CanonicalJsonObject) -> Result {
+	let sender: &UserId = pdu
+		.get("sender")
+		.try_into()
+		.map_err(|e| err!(Request(InvalidParam("PDU does not have a valid sender key: {e}"))))?;
+
+	if let Some(pdu_origin) = pdu.get("origin") {
+		if pdu_origin.as_str() != Some(origin.as_str()) {
+			return Err!(Request(Forbidden(
+				"PDU origin {pdu_origin:?} does not match transaction origin {origin}"
+			)));
+		}
+	}
+
+	if sender.server_name() != origin && !is_third_party_invite_member(pdu) {
+		return Err!(Request(Forbidden(
+			"Server {origin} cannot send PDUs on behalf of {sender}"
+		)));
+	}
+
+	Ok(())
+}
+
+fn is_third_party_invite_member(pdu: &CanonicalJsonObject) -> bool {
+	let is_member = pdu
+		.get("type")
+		.and_then(CanonicalJsonValue::as_str)
+		.is_some_and(|kind| kind == "m.room.member");
+
+	let has_third_party_invite = pdu
+		.get("content")
+		.and_then(CanonicalJsonValue::as_object)
+		.is_some_and(|content| content.contains_key("third_party_invite"));
+
+	is_member && has_third_party_invite
+}
@@ -0,0 +1,25 @@
+use conduwuit::{err, Err, Result};
+use ruma::{CanonicalJsonObject, CanonicalJsonValue};
+
+/// Checks that the `origin_server_ts` of a PDU is no more than `max_skew`
+/// milliseconds ahead of `now`, both in milliseconds since the unix epoch.
+pub fn check_event_timestamp(pdu: &CanonicalJsonObject, now: u64, max_skew: u64) -> Result {
+	let origin_server_ts = pdu
+		.get("origin_server_ts")
+		.and_then(|ts| match ts {
+			| CanonicalJsonValue::Integer(ts) => u64::try_from(i64::from(*ts)).ok(),
+			| _ => None,
+		})
+		.ok_or_else(|| {
+			err!(Request(InvalidParam("PDU does not have a valid origin_server_ts")))
+		})?;
+
+	if origin_server_ts > now.saturating_add(max_skew) {
+		return Err!(Request(InvalidParam(
+			"PDU origin_server_ts {origin_server_ts} is more than {max_skew}ms ahead of our clock \
+			 ({now})"
+		)));
+	}
+
+	Ok(())
+}
@@ -1,4 +1,7 @@
 mod acl_check;
+mod check_depth;
+mod check_origin;
+mod check_timestamp;
 mod fetch_and_handle_outliers;
 mod fetch_prev;
 mod fetch_state;
@@ -8,6 +11,7 @@ mod handle_prev_pdu;
 mod parse_incoming_pdu;
 mod resolve_state;
 mod state_at_incoming;
+mod tests;
 mod upgrade_outlier_pdu;
 
 use std::{
@@ -27,6 +31,10 @@ use ruma::{
 	OwnedRoomId, RoomId, RoomVersionId,
 };
 
+pub use self::{
+	check_depth::check_event_depth, check_origin::check_event_origin,
+	check_timestamp::check_event_timestamp,
+};
 use crate::{globals, rooms, sending, server_keys, Dep};
 
 pub struct Service {
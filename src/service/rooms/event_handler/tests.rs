@@ -0,0 +1,106 @@
+#![cfg(test)]
+
+use ruma::{server_name, CanonicalJsonObject};
+use serde_json::json;
+
+use super::{check_event_depth, check_event_origin, check_event_timestamp};
+
+fn pdu(value: serde_json::Value) -> CanonicalJsonObject {
+	serde_json::from_value(value).expect("valid canonical json")
+}
+
+#[test]
+fn event_origin_matching_sender() {
+	let pdu = pdu(json!({
+		"type": "m.room.message",
+		"sender": "@alice:example.org",
+		"origin": "example.org",
+		"content": {},
+	}));
+
+	assert!(check_event_origin(server_name!("example.org"), &pdu).is_ok());
+}
+
+#[test]
+fn event_origin_spoofed_sender() {
+	let pdu = pdu(json!({
+		"type": "m.room.message",
+		"sender": "@alice:example.com",
+		"content": {},
+	}));
+
+	assert!(check_event_origin(server_name!("example.org"), &pdu).is_err());
+}
+
+#[test]
+fn event_origin_mismatched_origin_field() {
+	let pdu = pdu(json!({
+		"type": "m.room.message",
+		"sender": "@alice:example.org",
+		"origin": "example.com",
+		"content": {},
+	}));
+
+	assert!(check_event_origin(server_name!("example.org"), &pdu).is_err());
+}
+
+#[test]
+fn event_origin_third_party_invite() {
+	let pdu = pdu(json!({
+		"type": "m.room.member",
+		"sender": "@alice:example.com",
+		"state_key": "@bob:example.org",
+		"content": {
+			"membership": "invite",
+			"third_party_invite": {},
+		},
+	}));
+
+	assert!(check_event_origin(server_name!("example.org"), &pdu).is_ok());
+}
+
+#[test]
+fn event_timestamp_within_skew() {
+	let now = 1_700_000_000_000;
+	let pdu = pdu(json!({
+		"type": "m.room.message",
+		"origin_server_ts": now + 60_000,
+		"content": {},
+	}));
+
+	assert!(check_event_timestamp(&pdu, now, 300_000).is_ok());
+}
+
+#[test]
+fn event_timestamp_far_in_future() {
+	let now = 1_700_000_000_000;
+	let pdu = pdu(json!({
+		"type": "m.room.message",
+		"origin_server_ts": now + 3_600_000,
+		"content": {},
+	}));
+
+	assert!(check_event_timestamp(&pdu, now, 300_000).is_err());
+}
+
+#[test]
+fn event_depth_just_above_leaves() {
+	let pdu = pdu(json!({
+		"type": "m.room.message",
+		"depth": 43,
+		"content": {},
+	}));
+
+	assert!(check_event_depth(&pdu, 42, 100_000).is_ok());
+}
+
+#[test]
+fn event_depth_absurd() {
+	let pdu = pdu(json!({
+		"type": "m.room.message",
+		"depth": 9_007_199_254_740_991_u64,
+		"content": {},
+	}));
+
+	assert!(check_event_depth(&pdu, 42, 100_000).is_err());
+}
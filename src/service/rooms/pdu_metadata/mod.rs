@@ -1,9 +1,16 @@
 mod data;
+mod tests;
+
 use std::sync::Arc;
 
-use conduwuit::{PduCount, Result};
+use conduwuit::{at, utils::ReadyExt, PduCount, PduEvent, Result};
 use futures::StreamExt;
-use ruma::{api::Direction, EventId, RoomId, UserId};
+use ruma::{
+	api::Direction,
+	events::room::encrypted::{Relation, Replacement},
+	EventId, RoomId, UInt, UserId,
+};
+use serde::Deserialize;
 
 use self::data::{Data, PdusIterItem};
 use crate::{rooms, Dep};
@@ -98,6 +105,43 @@ impl Service {
 		pdus
 	}
 
+	/// Returns the most recent valid edit (`m.replace`) of `pdu`, if any: the
+	/// one with the greatest `origin_server_ts`, ties broken by event ID.
+	pub async fn latest_edit(&self, user_id: &UserId, pdu: &PduEvent) -> Option<PduEvent> {
+		if pdu.state_key.is_some() {
+			return None;
+		}
+
+		let Ok(PduCount::Normal(target)) =
+			self.services.timeline.get_pdu_count(&pdu.event_id).await
+		else {
+			return None;
+		};
+
+		let shortroomid = self
+			.services
+			.short
+			.get_shortroomid(&pdu.room_id)
+			.await
+			.ok()?;
+		let relations = self
+			.db
+			.get_relations(user_id, shortroomid, target, PduCount::max(), Direction::Backward)
+			.ready_filter(|(_, edit)| is_valid_edit(pdu, edit))
+			.map(at!(1));
+
+		relations.ready_fold(None, newer_edit).await
+	}
+
+	/// Bundles the most recent valid edit of `pdu` into its
+	/// `unsigned["m.relations"]["m.replace"]`.
+	pub async fn add_bundled_edit(&self, user_id: &UserId, pdu: &mut PduEvent) -> Result {
+		match self.latest_edit(user_id, pdu).await {
+			| Some(edit) => pdu.add_relation("m.replace", &edit),
+			| None => Ok(()),
+		}
+	}
+
 	#[inline]
 	#[tracing::instrument(skip_all, level = "debug")]
 	pub fn mark_as_referenced(&self, room_id: &RoomId, event_ids: &[Arc<EventId>]) {
@@ -122,3 +166,38 @@ impl Service {
 		self.db.is_event_soft_failed(event_id).await
 	}
 }
+
+#[derive(Deserialize)]
+struct ExtractRelatesTo {
+	#[serde(rename = "m.relates_to")]
+	relates_to: Relation,
+}
+
+/// Picks whichever of `latest` and `edit` should be applied.
+fn newer_edit(latest: Option<PduEvent>, edit: PduEvent) -> Option<PduEvent> {
+	match latest {
+		| Some(latest) if edit_order(&latest) >= edit_order(&edit) => Some(latest),
+		| _ => Some(edit),
+	}
+}
+
+fn edit_order(pdu: &PduEvent) -> (UInt, &EventId) { (pdu.origin_server_ts, &*pdu.event_id) }
+
+/// Whether `edit` validly replaces `original`: both are non-state events of
+/// the same type and sender, `edit` relates to `original` with `m.replace`,
+/// and `original` is not itself an edit.
+fn is_valid_edit(original: &PduEvent, edit: &PduEvent) -> bool {
+	let replaces = |pdu: &PduEvent| match pdu.get_content::<ExtractRelatesTo>() {
+		| Ok(ExtractRelatesTo {
+			relates_to: Relation::Replacement(Replacement { event_id, .. }),
+		}) => Some(event_id),
+		| _ => None,
+	};
+
+	original.state_key.is_none()
+		&& edit.state_key.is_none()
+		&& edit.sender == original.sender
+		&& edit.kind == original.kind
+		&& replaces(original).is_none()
+		&& replaces(edit).is_some_and(|event_id| *event_id == *original.event_id)
+}
@@ -0,0 +1,93 @@
+#![cfg(test)]
+
+use conduwuit::PduEvent;
+use serde_json::{json, Value as JsonValue};
+
+use super::{is_valid_edit, newer_edit};
+use crate::rooms::tests::pdu;
+
+fn message(event_id: &str, sender: &str, content: JsonValue) -> PduEvent {
+	pdu(json!({ "event_id": event_id, "sender": sender, "content": content }))
+}
+
+fn edit(event_id: &str, sender: &str, original: &str, body: &str) -> PduEvent {
+	message(
+		event_id,
+		sender,
+		json!({
+			"msgtype": "m.text",
+			"body": format!("* {body}"),
+			"m.new_content": { "msgtype": "m.text", "body": body },
+			"m.relates_to": { "rel_type": "m.replace", "event_id": original },
+		}),
+	)
+}
+
+fn edit_at(event_id: &str, origin_server_ts: u64, body: &str) -> PduEvent {
+	let mut edit = edit(event_id, "@alice:example.org", "$original", body);
+	edit.origin_server_ts = origin_server_ts.try_into().expect("valid timestamp");
+	edit
+}
+
+fn latest(edits: Vec<PduEvent>) -> Option<String> {
+	edits
+		.into_iter()
+		.fold(None, newer_edit)
+		.map(|edit| edit.event_id.to_string())
+}
+
+#[test]
+fn edit_by_original_sender() {
+	let original =
+		message("$original", "@alice:example.org", json!({ "msgtype": "m.text", "body": "hi" }));
+
+	let first = edit("$first", "@alice:example.org", "$original", "hello");
+	let second = edit("$second", "@alice:example.org", "$original", "hello there");
+	assert!(is_valid_edit(&original, &first));
+	assert!(is_valid_edit(&original, &second));
+}
+
+#[test]
+fn edit_by_other_sender() {
+	let original =
+		message("$original", "@alice:example.org", json!({ "msgtype": "m.text", "body": "hi" }));
+
+	let edit = edit("$edit", "@mallory:example.org", "$original", "pwned");
+	assert!(!is_valid_edit(&original, &edit));
+}
+
+#[test]
+fn edit_of_other_event() {
+	let original =
+		message("$original", "@alice:example.org", json!({ "msgtype": "m.text", "body": "hi" }));
+
+	let edit = edit("$edit", "@alice:example.org", "$unrelated", "hello");
+	assert!(!is_valid_edit(&original, &edit));
+}
+
+#[test]
+fn edit_of_edit() {
+	let original = edit("$first", "@alice:example.org", "$original", "hello");
+
+	let edit = edit("$second", "@alice:example.org", "$first", "hello there");
+	assert!(!is_valid_edit(&original, &edit));
+}
+
+#[test]
+fn edit_twice_second_wins() {
+	let first = || edit_at("$first", 1_000, "hello");
+	let second = || edit_at("$second", 2_000, "hello there");
+
+	// relations are walked newest first, but arrival order must not matter
+	assert_eq!(latest(vec![second(), first()]).as_deref(), Some("$second"));
+	assert_eq!(latest(vec![first(), second()]).as_deref(), Some("$second"));
+}
+
+#[test]
+fn edit_timestamp_tie_broken_by_event_id() {
+	let a = || edit_at("$a", 1_000, "hello");
+	let b = || edit_at("$b", 1_000, "hello there");
+
+	assert_eq!(latest(vec![a(), b()]).as_deref(), Some("$b"));
+	assert_eq!(latest(vec![b(), a()]).as_deref(), Some("$b"));
+}
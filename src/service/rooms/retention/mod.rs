@@ -0,0 +1,219 @@
+mod tests;
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use conduwuit::{
+	debug, debug_info, utils, utils::TryReadyExt, warn, PduCount, PduEvent, Result, Server,
+};
+use database::{Deserialized, Map};
+use futures::{future::ready, StreamExt, TryStreamExt};
+use ruma::{events::StateEventType, OwnedEventId, OwnedRoomId, RoomId, RoomVersionId};
+use serde::Deserialize;
+use tokio::{
+	sync::Notify,
+	time::{interval, MissedTickBehavior},
+};
+
+use crate::{rooms, Dep};
+
+pub struct Service {
+	interrupt: Notify,
+	db: Data,
+	services: Services,
+}
+
+struct Data {
+	/// Per room, the count of the newest event already past the retention
+	/// period which has been handled; later passes resume after it.
+	roomid_expiredcount: Arc<Map>,
+}
+
+struct Services {
+	server: Arc<Server>,
+	metadata: Dep<rooms::metadata::Service>,
+	short: Dep<rooms::short::Service>,
+	state: Dep<rooms::state::Service>,
+	state_accessor: Dep<rooms::state_accessor::Service>,
+	state_cache: Dep<rooms::state_cache::Service>,
+	timeline: Dep<rooms::timeline::Service>,
+}
+
+/// Content of an `m.room.retention` state event.
+#[derive(Deserialize)]
+struct RoomRetentionEventContent {
+	/// Maximum age of events in milliseconds.
+	max_lifetime: Option<u64>,
+}
+
+#[async_trait]
+impl crate::Service for Service {
+	fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
+		Ok(Arc::new(Self {
+			interrupt: Notify::new(),
+			db: Data {
+				roomid_expiredcount: args.db["roomid_expiredcount"].clone(),
+			},
+			services: Services {
+				server: args.server.clone(),
+				metadata: args.depend::<rooms::metadata::Service>("rooms::metadata"),
+				short: args.depend::<rooms::short::Service>("rooms::short"),
+				state: args.depend::<rooms::state::Service>("rooms::state"),
+				state_accessor: args
+					.depend::<rooms::state_accessor::Service>("rooms::state_accessor"),
+				state_cache: args.depend::<rooms::state_cache::Service>("rooms::state_cache"),
+				timeline: args.depend::<rooms::timeline::Service>("rooms::timeline"),
+			},
+		}))
+	}
+
+	#[tracing::instrument(skip_all, name = "retention", level = "debug")]
+	async fn worker(self: Arc<Self>) -> Result<()> {
+		let config = &self.services.server.config;
+		if !config.allow_retention {
+			debug!("Disabling event retention purging");
+			return Ok(());
+		}
+
+		let period = Duration::from_secs(config.retention_interval_s);
+		let mut i = interval(period);
+		i.set_missed_tick_behavior(MissedTickBehavior::Delay);
+		i.reset_after(period);
+		loop {
+			tokio::select! {
+				() = self.interrupt.notified() => break,
+				_ = i.tick() => (),
+			}
+
+			self.apply_retention_all().await;
+		}
+
+		Ok(())
+	}
+
+	fn interrupt(&self) { self.interrupt.notify_waiters(); }
+
+	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
+}
+
+impl Service {
+	async fn apply_retention_all(&self) {
+		let room_ids: Vec<OwnedRoomId> = self
+			.services
+			.metadata
+			.iter_ids()
+			.map(ToOwned::to_owned)
+			.collect()
+			.await;
+
+		for room_id in &room_ids {
+			if !self.services.server.running() {
+				break;
+			}
+
+			// Nobody here can read the room; leave it alone until someone joins.
+			if !self
+				.services
+				.state_cache
+				.server_in_room(&self.services.server.config.server_name, room_id)
+				.await
+			{
+				continue;
+			}
+
+			match self.apply_retention(room_id).await {
+				| Ok(0) => (),
+				| Ok(purged) => debug_info!(%room_id, "Purged {purged} expired events"),
+				| Err(e) => warn!(%room_id, "Failed to apply retention policy: {e}"),
+			}
+		}
+	}
+
+	/// Purges the content of message events in the room which are older than
+	/// its retention policy allows. Returns the number of events purged.
+	///
+	/// Events are visited in timeline order starting after those handled by the
+	/// previous pass, up to the first event still within the retention period.
+	pub async fn apply_retention(&self, room_id: &RoomId) -> Result<usize> {
+		let Some(max_lifetime) = self.max_lifetime(room_id).await else {
+			return Ok(0);
+		};
+
+		let cutoff = utils::millis_since_unix_epoch().saturating_sub(max_lifetime);
+		let room_version_id = self.services.state.get_room_version(room_id).await?;
+		let from = self
+			.db
+			.roomid_expiredcount
+			.get(room_id)
+			.await
+			.deserialized()
+			.map(PduCount::from_unsigned)
+			.ok();
+
+		let (until, expired) = self
+			.services
+			.timeline
+			.pdus(None, room_id, from)
+			.await?
+			.try_take_while(|(_, pdu)| ready(Ok(u64::from(pdu.origin_server_ts) < cutoff)))
+			.ready_try_fold((from, Vec::new()), |(_, mut expired), (count, pdu)| {
+				if is_expired(&pdu, cutoff, &room_version_id) {
+					expired.push((*pdu.event_id).to_owned());
+				}
+
+				Ok((Some(count), expired))
+			})
+			.await?;
+
+		if !expired.is_empty() {
+			let shortroomid = self.services.short.get_shortroomid(room_id).await?;
+			let state_lock = self.services.state.mutex.lock(room_id).await;
+			for event_id in &expired {
+				self.services
+					.timeline
+					.expire_pdu(event_id, shortroomid, &state_lock)
+					.await?;
+			}
+		}
+
+		if let Some(until) = until {
+			self.db
+				.roomid_expiredcount
+				.raw_put(room_id, until.into_unsigned());
+		}
+
+		Ok(expired.len())
+	}
+
+	/// Maximum event age in milliseconds from the room's `m.room.retention`
+	/// policy, falling back to the server default.
+	async fn max_lifetime(&self, room_id: &RoomId) -> Option<u64> {
+		self.services
+			.state_accessor
+			.room_state_get_content(room_id, &StateEventType::from("m.room.retention"), "")
+			.await
+			.ok()
+			.and_then(|content: RoomRetentionEventContent| content.max_lifetime)
+			.or_else(|| {
+				self.services
+					.server
+					.config
+					.retention_default_max_lifetime_s
+					.map(|lifetime| lifetime.saturating_mul(1000))
+			})
+	}
+}
+
+/// Whether a message event is older than `cutoff` and still has content which
+/// redaction would remove. State events are never expired.
+fn is_expired(pdu: &PduEvent, cutoff: u64, room_version_id: &RoomVersionId) -> bool {
+	if pdu.state_key.is_some() || u64::from(pdu.origin_server_ts) >= cutoff {
+		return false;
+	}
+
+	let content =
+		|pdu: &PduEvent| serde_json::from_str::<serde_json::Value>(pdu.content.get()).ok();
+
+	let mut redacted = pdu.clone();
+	redacted.redact_content(room_version_id).is_ok() && content(&redacted) != content(pdu)
+}
@@ -0,0 +1,54 @@
+#![cfg(test)]
+
+use conduwuit::PduEvent;
+use ruma::RoomVersionId;
+use serde_json::{json, Value as JsonValue};
+
+use super::is_expired;
+use crate::rooms::tests::pdu;
+
+fn message(origin_server_ts: u64, content: JsonValue) -> PduEvent {
+	pdu(json!({ "origin_server_ts": origin_server_ts, "content": content }))
+}
+
+#[test]
+fn retention_expires_old_messages() {
+	let content = json!({ "msgtype": "m.text", "body": "hello" });
+	let old = message(1_000, content.clone());
+	let new = message(3_000, content);
+
+	assert!(is_expired(&old, 2_000, &RoomVersionId::V10));
+	assert!(!is_expired(&new, 2_000, &RoomVersionId::V10));
+}
+
+#[test]
+fn retention_skips_state_and_purged_events() {
+	let state = pdu(json!({
+		"origin_server_ts": 1_000,
+		"type": "m.room.name",
+		"state_key": "",
+		"content": { "name": "room" },
+	}));
+	let purged = message(1_000, json!({}));
+
+	assert!(!is_expired(&state, 2_000, &RoomVersionId::V10));
+	assert!(!is_expired(&purged, 2_000, &RoomVersionId::V10));
+}
+
+#[test]
+fn retention_skips_content_kept_by_redaction() {
+	let redaction = |content| {
+		pdu(json!({
+			"origin_server_ts": 1_000,
+			"type": "m.room.redaction",
+			"content": content,
+		}))
+	};
+
+	// v11 keeps `redacts` in the content of a redacted redaction
+	let purged = redaction(json!({ "redacts": "$target:example.org" }));
+	let reason = redaction(json!({ "redacts": "$target:example.org", "reason": "spam" }));
+
+	assert!(!is_expired(&purged, 2_000, &RoomVersionId::V11));
+	assert!(is_expired(&reason, 2_000, &RoomVersionId::V11));
+}
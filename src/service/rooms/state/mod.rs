@@ -1,3 +1,5 @@
+mod tests;
+
 use std::{
 	collections::{HashMap, HashSet},
 	fmt::Write,
@@ -21,7 +23,7 @@ use futures::{
 };
 use ruma::{
 	events::{
-		room::{create::RoomCreateEventContent, member::RoomMemberEventContent},
+		room::member::RoomMemberEventContent,
 		AnyStrippedStateEvent, StateEventType, TimelineEventType,
 	},
 	serde::Raw,
@@ -364,6 +366,11 @@ impl Service {
 		self.db
 			.roomid_shortstatehash
 			.raw_aput::<BUFSIZE, _, _>(room_id, shortstatehash);
+
+		self.services
+			.state_accessor
+			.encrypted_room_cache
+			.invalidate(room_id);
 	}
 
 	/// Returns the room's version.
@@ -371,9 +378,9 @@ impl Service {
 	pub async fn get_room_version(&self, room_id: &RoomId) -> Result<RoomVersionId> {
 		self.services
 			.state_accessor
-			.room_state_get_content(room_id, &StateEventType::RoomCreate, "")
+			.get_create_content(room_id)
 			.await
-			.map(|content: RoomCreateEventContent| content.room_version)
+			.map(|content| content.room_version)
 			.map_err(|e| err!(Request(NotFound("No create event found: {e:?}"))))
 	}
 
@@ -398,6 +405,41 @@ impl Service {
 			.ignore_err()
 	}
 
+	/// Returns the greatest depth among the room's forward extremities, or None
+	/// if we hold none of them.
+	pub async fn max_forward_extremity_depth(&self, room_id: &RoomId) -> Option<u64> {
+		self.get_forward_extremities(room_id)
+			.filter_map(|event_id| async move {
+				self.services.timeline.get_pdu(event_id).await.ok()
+			})
+			.ready_fold(None, |max, pdu| max.max(Some(pdu.depth.into())))
+			.await
+	}
+
+	/// Rebuilds the room's forward extremities from its timeline: every event
+	/// which no other event lists in its prev_events. This repairs
+	/// roomid_pduleaves when it no longer matches the timeline.
+	pub async fn recompute_forward_extremities(
+		&self,
+		room_id: &RoomId,
+		state_lock: &RoomMutexGuard,
+	) -> Result<Vec<OwnedEventId>> {
+		let events: Vec<_> = self
+			.services
+			.timeline
+			.pdus(None, room_id, None)
+			.await?
+			.map_ok(|(_, pdu)| (pdu.event_id, pdu.prev_events))
+			.try_collect()
+			.await?;
+
+		let leaves = unreferenced_events(&events);
+		self.set_forward_extremities(room_id, leaves.clone(), state_lock)
+			.await;
+
+		Ok(leaves)
+	}
+
 	pub async fn set_forward_extremities(
 		&self,
 		room_id: &RoomId,
@@ -419,6 +461,19 @@ impl Service {
 		}
 	}
 
+	/// Returns the room's forward extremities as they will be once `pdu` is
+	/// appended. The events it references stop being extremities, while any
+	/// concurrent extremities are kept.
+	pub async fn forward_extremities_after(&self, pdu: &PduEvent) -> Vec<OwnedEventId> {
+		let leaves = self
+			.get_forward_extremities(&pdu.room_id)
+			.map(ToOwned::to_owned)
+			.collect()
+			.await;
+
+		replace_referenced_leaves(leaves, &pdu.prev_events, &pdu.event_id)
+	}
+
 	/// This fetches auth events from the current state.
 	#[tracing::instrument(skip(self, content), level = "debug")]
 	pub async fn get_auth_events(
@@ -485,3 +540,34 @@ impl Service {
 		Ok(auth_pdus)
 	}
 }
+
+/// Removes the leaves referenced by `prev_events` and adds `event_id` as a new
+/// leaf.
+fn replace_referenced_leaves(
+	mut leaves: Vec<OwnedEventId>,
+	prev_events: &[Arc<EventId>],
+	event_id: &EventId,
+) -> Vec<OwnedEventId> {
+	leaves.retain(|leaf| !prev_events.iter().any(|prev| **prev == **leaf));
+	if !leaves.iter().any(|leaf| **leaf == *event_id) {
+		leaves.push(event_id.to_owned());
+	}
+
+	leaves
+}
+
+/// Returns the events which no other event in `events` lists in its
+/// prev_events, in the order given.
+fn unreferenced_events(events: &[(Arc<EventId>, Vec<Arc<EventId>>)]) -> Vec<OwnedEventId> {
+	let referenced: HashSet<&EventId> = events
+		.iter()
+		.flat_map(|(_, prev_events)| prev_events)
+		.map(|prev_event| &**prev_event)
+		.collect();
+
+	events
+		.iter()
+		.filter(|(event_id, _)| !referenced.contains(&**event_id))
+		.map(|(event_id, _)| (**event_id).to_owned())
+		.collect()
+}
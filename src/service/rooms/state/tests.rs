@@ -0,0 +1,60 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+
+use ruma::{event_id, owned_event_id, EventId};
+
+use super::{replace_referenced_leaves, unreferenced_events};
+
+#[test]
+fn referenced_leaves_are_replaced() {
+	let a = owned_event_id!("$a:example.org");
+	let prev: Vec<Arc<EventId>> = vec![event_id!("$a:example.org").into()];
+
+	let leaves = replace_referenced_leaves(vec![a], &prev, event_id!("$b:example.org"));
+	assert_eq!(leaves, vec![owned_event_id!("$b:example.org")]);
+}
+
+#[test]
+fn forked_leaves_are_kept_until_referenced() {
+	let root: Vec<Arc<EventId>> = vec![event_id!("$root:example.org").into()];
+
+	// two events sharing the same prev event fork the DAG
+	let leaves = vec![owned_event_id!("$root:example.org")];
+	let leaves = replace_referenced_leaves(leaves, &root, event_id!("$left:example.org"));
+	let leaves = replace_referenced_leaves(leaves, &root, event_id!("$right:example.org"));
+	assert_eq!(leaves, vec![
+		owned_event_id!("$left:example.org"),
+		owned_event_id!("$right:example.org")
+	]);
+
+	// an event referencing only one side leaves the other side in place
+	let left: Vec<Arc<EventId>> = vec![event_id!("$left:example.org").into()];
+	let leaves = replace_referenced_leaves(leaves, &left, event_id!("$next:example.org"));
+	assert_eq!(leaves, vec![
+		owned_event_id!("$right:example.org"),
+		owned_event_id!("$next:example.org")
+	]);
+}
+
+#[test]
+fn unreferenced_events_are_the_leaves() {
+	let create: Arc<EventId> = event_id!("$create:example.org").into();
+	let left: Arc<EventId> = event_id!("$left:example.org").into();
+	let right: Arc<EventId> = event_id!("$right:example.org").into();
+	let merge: Arc<EventId> = event_id!("$merge:example.org").into();
+	let fork: Arc<EventId> = event_id!("$fork:example.org").into();
+
+	let events = vec![
+		(create.clone(), vec![]),
+		(left.clone(), vec![create.clone()]),
+		(right.clone(), vec![create]),
+		(merge, vec![left.clone(), right]),
+		(fork, vec![left]),
+	];
+
+	assert_eq!(unreferenced_events(&events), vec![
+		owned_event_id!("$merge:example.org"),
+		owned_event_id!("$fork:example.org")
+	]);
+}
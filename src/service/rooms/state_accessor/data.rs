@@ -6,7 +6,7 @@ use conduwuit::{
 	PduEvent, Result,
 };
 use database::{Deserialized, Map};
-use futures::{FutureExt, StreamExt, TryFutureExt};
+use futures::{FutureExt, Stream, StreamExt, TryFutureExt};
 use ruma::{events::StateEventType, EventId, OwnedEventId, RoomId};
 use serde::Deserialize;
 
@@ -218,6 +218,38 @@ impl Data {
 			.await
 	}
 
+	/// Streams the full room state's pdus without collecting them.
+	pub(super) async fn room_state_pdus<'a>(
+		&'a self,
+		room_id: &'a RoomId,
+	) -> Result<impl Stream<Item = PduEvent> + Send + 'a> {
+		let short_ids = self
+			.services
+			.state
+			.get_room_shortstatehash(room_id)
+			.and_then(|shortstatehash| self.state_full_shortids(shortstatehash))
+			.map_err(|e| err!(Database("Missing state pdus for {room_id:?}: {e:?}")))
+			.await?;
+
+		let pdus = short_ids
+			.into_iter()
+			.stream()
+			.broad_filter_map(move |(_, shorteventid)| self.state_pdu(shorteventid));
+
+		Ok(pdus)
+	}
+
+	async fn state_pdu(&self, shorteventid: ShortEventId) -> Option<PduEvent> {
+		let event_id: OwnedEventId = self
+			.services
+			.short
+			.get_eventid_from_short(shorteventid)
+			.await
+			.ok()?;
+
+		self.services.timeline.get_pdu(&event_id).await.ok()
+	}
+
 	/// Returns a single EventId from `room_id` with key
 	/// (`event_type`,`state_key`).
 	pub(super) async fn room_state_get_id<Id>(
@@ -1,10 +1,16 @@
 mod data;
+mod tests;
 
 use std::{
 	borrow::Borrow,
 	collections::HashMap,
 	fmt::Write,
-	sync::{Arc, Mutex as StdMutex, Mutex},
+	ops::ControlFlow,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex as StdMutex, Mutex,
+	},
+	time::Instant,
 };
 
 use conduwuit::{
@@ -13,7 +19,7 @@ use conduwuit::{
 	utils::{math::usize_from_f64, ReadyExt},
 	Err, Error, PduEvent, Result,
 };
-use futures::StreamExt;
+use futures::{pin_mut, Stream, StreamExt};
 use lru_cache::LruCache;
 use ruma::{
 	events::{
@@ -27,6 +33,7 @@ use ruma::{
 			join_rules::{AllowRule, JoinRule, RoomJoinRulesEventContent, RoomMembership},
 			member::{MembershipState, RoomMemberEventContent},
 			name::RoomNameEventContent,
+			pinned_events::RoomPinnedEventsEventContent,
 			power_levels::{RoomPowerLevels, RoomPowerLevelsEventContent},
 			topic::RoomTopicEventContent,
 		},
@@ -34,10 +41,11 @@ use ruma::{
 	},
 	room::RoomType,
 	space::SpaceRoomJoinRule,
-	EventEncryptionAlgorithm, EventId, JsOption, OwnedRoomAliasId, OwnedRoomId, OwnedServerName,
-	OwnedUserId, RoomId, ServerName, UserId,
+	EventEncryptionAlgorithm, EventId, JsOption, OwnedEventId, OwnedRoomAliasId, OwnedRoomId,
+	OwnedServerName, OwnedUserId, RoomId, ServerName, UserId,
 };
 use serde::Deserialize;
+use tracing::{field, Span};
 
 use self::data::Data;
 use crate::{
@@ -54,6 +62,16 @@ pub struct Service {
 	db: Data,
 	pub server_visibility_cache: Mutex<LruCache<(OwnedServerName, ShortStateHash), bool>>,
 	pub user_visibility_cache: Mutex<LruCache<(OwnedUserId, ShortStateHash), bool>>,
+	pub encrypted_room_cache: EncryptedRoomCache,
+}
+
+/// Whether rooms have encryption enabled as of their current state. A room's
+/// entry is dropped whenever its current state changes.
+pub struct EncryptedRoomCache {
+	rooms: Mutex<LruCache<OwnedRoomId, bool>>,
+	/// Bumped by every invalidation, so an answer looked up while some room's
+	/// state changed is not cached.
+	generation: AtomicU64,
 }
 
 struct Services {
@@ -68,6 +86,8 @@ impl crate::Service for Service {
 			f64::from(config.server_visibility_cache_capacity) * config.cache_capacity_modifier;
 		let user_visibility_cache_capacity =
 			f64::from(config.user_visibility_cache_capacity) * config.cache_capacity_modifier;
+		let encrypted_room_cache_capacity =
+			f64::from(config.encrypted_room_cache_capacity) * config.cache_capacity_modifier;
 
 		Ok(Arc::new(Self {
 			services: Services {
@@ -81,6 +101,9 @@ impl crate::Service for Service {
 			user_visibility_cache: StdMutex::new(LruCache::new(usize_from_f64(
 				user_visibility_cache_capacity,
 			)?)),
+			encrypted_room_cache: EncryptedRoomCache::new(usize_from_f64(
+				encrypted_room_cache_capacity,
+			)?),
 		}))
 	}
 
@@ -91,12 +114,16 @@ impl crate::Service for Service {
 		let user_visibility_cache = self.user_visibility_cache.lock().expect("locked").len();
 		writeln!(out, "user_visibility_cache: {user_visibility_cache}")?;
 
+		let encrypted_room_cache = self.encrypted_room_cache.len();
+		writeln!(out, "encrypted_room_cache: {encrypted_room_cache}")?;
+
 		Ok(())
 	}
 
 	fn clear_cache(&self) {
 		self.server_visibility_cache.lock().expect("locked").clear();
 		self.user_visibility_cache.lock().expect("locked").clear();
+		self.encrypted_room_cache.clear();
 	}
 
 	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
@@ -352,18 +379,57 @@ impl Service {
 	}
 
 	/// Returns the full room state.
-	#[tracing::instrument(skip(self), level = "debug")]
+	#[tracing::instrument(
+		skip(self),
+		level = "debug",
+		fields(events = field::Empty, elapsed = field::Empty)
+	)]
 	pub async fn room_state_full(
 		&self,
 		room_id: &RoomId,
 	) -> Result<HashMap<(StateEventType, String), PduEvent>> {
-		self.db.room_state_full(room_id).await
+		let timer = Instant::now();
+		let state = self.db.room_state_full(room_id).await?;
+		record_loaded(state.len(), timer);
+
+		Ok(state)
 	}
 
 	/// Returns the full room state pdus
-	#[tracing::instrument(skip(self), level = "debug")]
+	#[tracing::instrument(
+		skip(self),
+		level = "debug",
+		fields(events = field::Empty, elapsed = field::Empty)
+	)]
 	pub async fn room_state_full_pdus(&self, room_id: &RoomId) -> Result<Vec<PduEvent>> {
-		self.db.room_state_full_pdus(room_id).await
+		let timer = Instant::now();
+		let pdus = self.db.room_state_full_pdus(room_id).await?;
+		record_loaded(pdus.len(), timer);
+
+		Ok(pdus)
+	}
+
+	/// Streams the full room state pdus. Prefer this over `room_state_full`
+	/// when only some of the state is needed, as large rooms can have tens of
+	/// thousands of state events.
+	#[inline]
+	pub async fn room_state_pdus<'a>(
+		&'a self,
+		room_id: &'a RoomId,
+	) -> Result<impl Stream<Item = PduEvent> + Send + 'a> {
+		self.db.room_state_pdus(room_id).await
+	}
+
+	/// Calls `f` with each state event of the room until it returns
+	/// `ControlFlow::Break`, without collecting the room state.
+	pub async fn for_each_state<F>(&self, room_id: &RoomId, f: F) -> Result
+	where
+		F: FnMut(PduEvent) -> ControlFlow<()> + Send,
+	{
+		let pdus = self.room_state_pdus(room_id).await?;
+		for_each_until(pdus, f).await;
+
+		Ok(())
 	}
 
 	/// Returns a single EventId from `room_id` with key (`event_type`,
@@ -492,6 +558,28 @@ impl Service {
 			.map(|c: RoomTopicEventContent| c.topic)
 	}
 
+	/// Gets the event IDs pinned in the room, in the order they were pinned.
+	/// Returns an empty list if the room has no `m.room.pinned_events` state.
+	pub async fn get_pinned_events(&self, room_id: &RoomId) -> Vec<OwnedEventId> {
+		self.room_state_get_content(room_id, &StateEventType::RoomPinnedEvents, "")
+			.await
+			.map(|c: RoomPinnedEventsEventContent| c.pinned)
+			.unwrap_or_default()
+	}
+
+	/// Checks that every event newly pinned by `pinned` exists in this room.
+	/// Events which are already pinned are not checked again, so the list can
+	/// still be changed when an older pinned event is unknown to us.
+	pub async fn check_pinned_events(&self, room_id: &RoomId, pinned: &[OwnedEventId]) -> Result {
+		let current = self.get_pinned_events(room_id).await;
+		for event_id in newly_pinned(pinned, &current) {
+			let pdu = self.services.timeline.get_pdu(event_id).await.ok();
+			check_pin(room_id, event_id, pdu.as_ref())?;
+		}
+
+		Ok(())
+	}
+
 	/// Checks if a given user can redact a given event
 	///
 	/// If federation is true, it allows redaction events from any user of the
@@ -586,14 +674,17 @@ impl Service {
 		room_ids
 	}
 
-	pub async fn get_room_type(&self, room_id: &RoomId) -> Result<RoomType> {
+	/// Gets the content of the room's `m.room.create` event, which holds its
+	/// creator, room version, room type and predecessor.
+	pub async fn get_create_content(&self, room_id: &RoomId) -> Result<RoomCreateEventContent> {
 		self.room_state_get_content(room_id, &StateEventType::RoomCreate, "")
 			.await
-			.and_then(|content: RoomCreateEventContent| {
-				content
-					.room_type
-					.ok_or_else(|| err!(Request(NotFound("No type found in event content"))))
-			})
+	}
+
+	/// Gets the room's type from its `m.room.create` event, such as `m.space`
+	/// for spaces.
+	pub async fn get_room_type(&self, room_id: &RoomId) -> Result<RoomType> {
+		self.get_create_content(room_id).await.and_then(room_type)
 	}
 
 	/// Gets the room's encryption algorithm if `m.room.encryption` state event
@@ -607,9 +698,105 @@ impl Service {
 			.map(|content: RoomEncryptionEventContent| content.algorithm)
 	}
 
+	/// Whether the room has encryption enabled. The answer is cached until the
+	/// room's state changes.
 	pub async fn is_encrypted_room(&self, room_id: &RoomId) -> bool {
-		self.room_state_get(room_id, &StateEventType::RoomEncryption, "")
+		if let Some(encrypted) = self.encrypted_room_cache.get(room_id) {
+			return encrypted;
+		}
+
+		let generation = self.encrypted_room_cache.generation();
+		let encrypted = self
+			.room_state_get(room_id, &StateEventType::RoomEncryption, "")
 			.await
-			.is_ok()
+			.is_ok();
+
+		self.encrypted_room_cache
+			.insert(room_id, encrypted, generation);
+		encrypted
+	}
+}
+
+/// Returns the events in `pinned` which are not already in `current`.
+fn newly_pinned<'a>(
+	pinned: &'a [OwnedEventId],
+	current: &'a [OwnedEventId],
+) -> impl Iterator<Item = &'a OwnedEventId> + 'a {
+	pinned
+		.iter()
+		.filter(move |event_id| !current.contains(event_id))
+}
+
+/// Checks that the newly pinned `event_id`, found locally as `pdu`, is an
+/// event of this room.
+fn check_pin(room_id: &RoomId, event_id: &EventId, pdu: Option<&PduEvent>) -> Result {
+	if !pdu.is_some_and(|pdu| pdu.room_id == room_id) {
+		return Err!(Request(NotFound("Pinned event {event_id} does not exist in this room.")));
+	}
+
+	Ok(())
+}
+
+/// Calls `f` with each pdu of `pdus` until it returns `ControlFlow::Break`.
+async fn for_each_until<S, F>(pdus: S, mut f: F)
+where
+	S: Stream<Item = PduEvent> + Send,
+	F: FnMut(PduEvent) -> ControlFlow<()> + Send,
+{
+	pin_mut!(pdus);
+	while let Some(pdu) = pdus.next().await {
+		if f(pdu).is_break() {
+			break;
+		}
+	}
+}
+
+/// Records how many state events were loaded since `timer` on the current
+/// span.
+fn record_loaded(events: usize, timer: Instant) {
+	let span = Span::current();
+	span.record("events", events);
+	span.record("elapsed", field::debug(timer.elapsed()));
+}
+
+/// Returns the room type set in `m.room.create` content.
+fn room_type(content: RoomCreateEventContent) -> Result<RoomType> {
+	content
+		.room_type
+		.ok_or_else(|| err!(Request(NotFound("No type found in event content"))))
+}
+
+impl EncryptedRoomCache {
+	fn new(capacity: usize) -> Self {
+		Self {
+			rooms: Mutex::new(LruCache::new(capacity)),
+			generation: AtomicU64::new(0),
+		}
+	}
+
+	fn get(&self, room_id: &RoomId) -> Option<bool> {
+		self.rooms.lock().expect("locked").get_mut(room_id).copied()
 	}
+
+	fn generation(&self) -> u64 { self.generation.load(Ordering::Acquire) }
+
+	/// Caches the answer looked up at `generation`, unless a room's state has
+	/// changed since.
+	fn insert(&self, room_id: &RoomId, encrypted: bool, generation: u64) {
+		let mut rooms = self.rooms.lock().expect("locked");
+		if self.generation() == generation {
+			rooms.insert(room_id.to_owned(), encrypted);
+		}
+	}
+
+	/// Forgets the room's entry; called whenever its current state changes.
+	pub fn invalidate(&self, room_id: &RoomId) {
+		let mut rooms = self.rooms.lock().expect("locked");
+		rooms.remove(room_id);
+		self.generation.fetch_add(1, Ordering::Release);
+	}
+
+	fn len(&self) -> usize { self.rooms.lock().expect("locked").len() }
+
+	fn clear(&self) { self.rooms.lock().expect("locked").clear(); }
 }
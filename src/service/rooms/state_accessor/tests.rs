@@ -0,0 +1,162 @@
+#![cfg(test)]
+
+use std::ops::ControlFlow;
+
+use conduwuit::{utils::IterStream, PduEvent};
+use ruma::{
+	event_id, events::room::create::RoomCreateEventContent, owned_event_id, room::RoomType,
+	room_id, RoomVersionId,
+};
+use serde_json::json;
+
+use super::{check_pin, for_each_until, newly_pinned, room_type, EncryptedRoomCache};
+use crate::rooms::tests::pdu;
+
+#[test]
+fn newly_pinned_skips_existing_pins() {
+	let current = vec![owned_event_id!("$old:example.org"), owned_event_id!("$kept:example.org")];
+	let pinned = vec![owned_event_id!("$kept:example.org"), owned_event_id!("$new:example.org")];
+
+	let added: Vec<_> = newly_pinned(&pinned, &current).collect();
+	assert_eq!(added, vec![&owned_event_id!("$new:example.org")]);
+}
+
+#[test]
+fn unpinning_checks_nothing() {
+	// $old is unknown locally; removing it from the list adds no new pins, so
+	// nothing has to be looked up and the change is allowed
+	let current = vec![owned_event_id!("$old:example.org"), owned_event_id!("$kept:example.org")];
+	let pinned = vec![owned_event_id!("$kept:example.org")];
+
+	assert_eq!(newly_pinned(&pinned, &current).count(), 0);
+}
+
+#[test]
+fn pinning_events_of_the_room() {
+	let room_id = room_id!("!room:example.org");
+	let current = vec![];
+	let pinned =
+		vec![owned_event_id!("$first:example.org"), owned_event_id!("$second:example.org")];
+
+	let added: Vec<_> = newly_pinned(&pinned, &current).collect();
+	assert_eq!(added.len(), 2);
+	for event_id in added {
+		let pdu = pdu(json!({ "event_id": event_id, "room_id": room_id }));
+		assert!(check_pin(room_id, event_id, Some(&pdu)).is_ok());
+	}
+}
+
+#[test]
+fn pinning_event_of_another_room_rejected() {
+	let event_id = event_id!("$other:example.org");
+	let pdu = pdu(json!({ "event_id": event_id, "room_id": "!other:example.org" }));
+
+	let result = check_pin(room_id!("!room:example.org"), event_id, Some(&pdu));
+	assert!(result.is_err_and(|e| e.is_not_found()));
+}
+
+#[test]
+fn pinning_unknown_event_rejected() {
+	let result =
+		check_pin(room_id!("!room:example.org"), event_id!("$unknown:example.org"), None);
+	assert!(result.is_err_and(|e| e.is_not_found()));
+}
+
+#[test]
+fn encrypted_room_cached_until_state_changes() {
+	let cache = EncryptedRoomCache::new(10);
+	let room_id = room_id!("!room:example.com");
+	assert_eq!(cache.get(room_id), None);
+
+	let generation = cache.generation();
+	cache.insert(room_id, false, generation);
+	assert_eq!(cache.get(room_id), Some(false));
+
+	// m.room.encryption was sent; the room is looked up again
+	cache.invalidate(room_id);
+	assert_eq!(cache.get(room_id), None);
+
+	let generation = cache.generation();
+	cache.insert(room_id, true, generation);
+	assert_eq!(cache.get(room_id), Some(true));
+}
+
+#[test]
+fn encrypted_room_lookup_racing_state_change_not_cached() {
+	let cache = EncryptedRoomCache::new(10);
+	let room_id = room_id!("!room:example.com");
+
+	// looked up as cleartext while the room's state was being changed
+	let generation = cache.generation();
+	cache.invalidate(room_id);
+	cache.insert(room_id, false, generation);
+
+	assert_eq!(cache.get(room_id), None);
+}
+
+#[test]
+fn space_create_content() {
+	let content: RoomCreateEventContent = serde_json::from_value(json!({
+		"room_version": "11",
+		"type": "m.space",
+	}))
+	.expect("valid create content");
+
+	assert_eq!(content.room_version, RoomVersionId::V11);
+	assert_eq!(room_type(content).expect("typed room"), RoomType::Space);
+}
+
+#[test]
+fn untyped_create_content() {
+	let content: RoomCreateEventContent = serde_json::from_value(json!({
+		"room_version": "10",
+	}))
+	.expect("valid create content");
+
+	assert_eq!(content.room_version, RoomVersionId::V10);
+	assert!(room_type(content).is_err_and(|e| e.is_not_found()));
+}
+
+fn member_events() -> Vec<PduEvent> {
+	["@alice:example.org", "@bob:example.org", "@carol:example.org"]
+		.into_iter()
+		.map(|user_id| {
+			pdu(json!({
+				"type": "m.room.member",
+				"state_key": user_id,
+				"content": { "membership": "join" },
+			}))
+		})
+		.collect()
+}
+
+#[tokio::test]
+async fn for_each_state_visits_every_event_once() {
+	let mut visited = Vec::new();
+	for_each_until(member_events().into_iter().stream(), |pdu| {
+		visited.push(pdu.state_key.clone().expect("state event"));
+		ControlFlow::Continue(())
+	})
+	.await;
+
+	assert_eq!(visited, ["@alice:example.org", "@bob:example.org", "@carol:example.org"]);
+}
+
+#[tokio::test]
+async fn for_each_state_stops_on_break() {
+	let mut visited = Vec::new();
+	for_each_until(member_events().into_iter().stream(), |pdu| {
+		let state_key = pdu.state_key.clone().expect("state event");
+		let found = state_key == "@bob:example.org";
+		visited.push(state_key);
+
+		if found {
+			ControlFlow::Break(())
+		} else {
+			ControlFlow::Continue(())
+		}
+	})
+	.await;
+
+	assert_eq!(visited, ["@alice:example.org", "@bob:example.org"]);
+}
@@ -16,7 +16,6 @@ use ruma::{
 	events::{
 		direct::DirectEvent,
 		room::{
-			create::RoomCreateEventContent,
 			member::{MembershipState, RoomMemberEventContent},
 			power_levels::RoomPowerLevelsEventContent,
 		},
@@ -53,6 +52,7 @@ struct Data {
 	roomuserid_leftcount: Arc<Map>,
 	roomuseroncejoinedids: Arc<Map>,
 	serverroomids: Arc<Map>,
+	userroomid_created: Arc<Map>,
 	userroomid_invitestate: Arc<Map>,
 	userroomid_joined: Arc<Map>,
 	userroomid_leftstate: Arc<Map>,
@@ -83,6 +83,7 @@ impl crate::Service for Service {
 				roomuserid_leftcount: args.db["roomuserid_leftcount"].clone(),
 				roomuseroncejoinedids: args.db["roomuseroncejoinedids"].clone(),
 				serverroomids: args.db["serverroomids"].clone(),
+				userroomid_created: args.db["userroomid_created"].clone(),
 				userroomid_invitestate: args.db["userroomid_invitestate"].clone(),
 				userroomid_joined: args.db["userroomid_joined"].clone(),
 				userroomid_leftstate: args.db["userroomid_leftstate"].clone(),
@@ -155,9 +156,9 @@ impl Service {
 					if let Ok(Some(predecessor)) = self
 						.services
 						.state_accessor
-						.room_state_get_content(room_id, &StateEventType::RoomCreate, "")
+						.get_create_content(room_id)
 						.await
-						.map(|content: RoomCreateEventContent| content.predecessor)
+						.map(|content| content.predecessor)
 					{
 						// Copy user settings from predecessor to the current room:
 						// - Push rules
@@ -330,6 +331,15 @@ impl Service {
 		self.db.roomid_inviteviaservers.remove(room_id);
 	}
 
+	/// Records that a local user created the room, see [`Self::rooms_created`].
+	#[tracing::instrument(skip(self), level = "debug")]
+	pub fn mark_as_created(&self, user_id: &UserId, room_id: &RoomId) {
+		let userroom_id = (user_id, room_id);
+		let userroom_id = serialize_key(userroom_id).expect("failed to serialize userroom_id");
+
+		self.db.userroomid_created.insert(&userroom_id, []);
+	}
+
 	/// Direct DB function to directly mark a user as left. It is not
 	/// recommended to use this directly. You most likely should use
 	/// `update_membership` instead
@@ -547,6 +557,20 @@ impl Service {
 			.map(|(_, room_id): (Ignore, &RoomId)| room_id)
 	}
 
+	/// Returns an iterator over all rooms this user created, whether or not
+	/// they are still joined.
+	#[tracing::instrument(skip(self), level = "debug")]
+	pub fn rooms_created<'a>(
+		&'a self,
+		user_id: &'a UserId,
+	) -> impl Stream<Item = &RoomId> + Send + 'a {
+		self.db
+			.userroomid_created
+			.keys_raw_prefix(user_id)
+			.ignore_err()
+			.map(|(_, room_id): (Ignore, &RoomId)| room_id)
+	}
+
 	/// Returns an iterator over all rooms a user was invited to.
 	#[tracing::instrument(skip(self), level = "debug")]
 	pub fn rooms_invited<'a>(
@@ -0,0 +1,27 @@
+#![cfg(test)]
+
+use conduwuit::PduEvent;
+use serde_json::{json, Value as JsonValue};
+
+/// Builds a PDU from `fields` laid over placeholder values for the rest.
+pub(super) fn pdu(fields: JsonValue) -> PduEvent {
+	let mut pdu = json!({
+		"event_id": "$event:example.org",
+		"room_id": "!room:example.org",
+		"sender": "@alice:example.org",
+		"origin_server_ts": 1_700_000_000_000_u64,
+		"type": "m.room.message",
+		"content": {},
+		"prev_events": [],
+		"depth": 1,
+		"auth_events": [],
+		"hashes": { "sha256": "" },
+	});
+
+	for (key, value) in fields.as_object().expect("fields are an object") {
+		pdu[key] = value.clone();
+	}
+
+	// content is a RawValue, which only deserializes from JSON text
+	serde_json::from_str(&pdu.to_string()).expect("valid pdu")
+}
@@ -4,17 +4,19 @@ use std::{
 	sync::Arc,
 };
 
+use arrayvec::ArrayVec;
 use conduwuit::{
 	at, err,
 	result::{LogErr, NotFound},
 	utils,
-	utils::{future::TryExtExt, stream::TryIgnore, ReadyExt},
-	Err, PduCount, PduEvent, Result,
+	utils::{future::TryExtExt, str_from_bytes, stream::TryIgnore, ReadyExt},
+	warn, Err, PduCount, PduEvent, Result,
 };
 use database::{Database, Deserialized, Json, KeyVal, Map};
 use futures::{future::select_ok, FutureExt, Stream, StreamExt};
 use ruma::{
-	api::Direction, CanonicalJsonObject, EventId, OwnedRoomId, OwnedUserId, RoomId, UserId,
+	api::Direction, CanonicalJsonObject, EventId, OwnedEventId, OwnedRoomId, OwnedUserId, RoomId,
+	UserId,
 };
 use tokio::sync::Mutex;
 
@@ -25,6 +27,7 @@ pub(super) struct Data {
 	eventid_outlierpdu: Arc<Map>,
 	eventid_pduid: Arc<Map>,
 	pduid_pdu: Arc<Map>,
+	roomid_ts_eventid: Arc<Map>,
 	userroomid_highlightcount: Arc<Map>,
 	userroomid_notificationcount: Arc<Map>,
 	pub(super) lasttimelinecount_cache: LastTimelineCountCache,
@@ -37,8 +40,19 @@ struct Services {
 }
 
 pub type PdusIterItem = (PduCount, PduEvent);
+
+/// Counts of dead entries removed by a garbage-collection pass.
+#[derive(Debug, Default)]
+pub struct GcReport {
+	/// `eventid_pduid` entries pointing at a PDU which no longer exists.
+	pub dangling_event_ids: usize,
+}
 type LastTimelineCountCache = Mutex<HashMap<OwnedRoomId, PduCount>>;
 
+/// Key of `roomid_ts_eventid`: shortroomid, origin_server_ts and the count of
+/// the event's pdu id, which keeps events sent at the same time apart.
+pub(super) type TimestampKey = ArrayVec<u8, 24>;
+
 impl Data {
 	pub(super) fn new(args: &crate::Args<'_>) -> Self {
 		let db = &args.db;
@@ -46,6 +60,7 @@ impl Data {
 			eventid_outlierpdu: db["eventid_outlierpdu"].clone(),
 			eventid_pduid: db["eventid_pduid"].clone(),
 			pduid_pdu: db["pduid_pdu"].clone(),
+			roomid_ts_eventid: db["roomid_ts_eventid"].clone(),
 			userroomid_highlightcount: db["userroomid_highlightcount"].clone(),
 			userroomid_notificationcount: db["userroomid_notificationcount"].clone(),
 			lasttimelinecount_cache: Mutex::new(HashMap::new()),
@@ -73,6 +88,7 @@ impl Data {
 				.await?
 				.next()
 				.await
+				.transpose()?
 				.map(at!(0))
 				.filter(|&count| matches!(count, PduCount::Normal(_)))
 				.map_or_else(PduCount::max, |count| *v.insert(count))),
@@ -121,7 +137,15 @@ impl Data {
 	pub(super) async fn get_non_outlier_pdu(&self, event_id: &EventId) -> Result<PduEvent> {
 		let pduid = self.get_pdu_id(event_id).await?;
 
-		self.pduid_pdu.get(&pduid).await.deserialized()
+		self.pduid_pdu
+			.get(&pduid)
+			.await
+			.inspect_err(|e| {
+				if e.is_not_found() {
+					warn!(?pduid, "eventid_pduid entry for {event_id} has no PDU");
+				}
+			})
+			.deserialized()
 	}
 
 	/// Like get_non_outlier_pdu(), but without the expense of fetching and
@@ -193,6 +217,33 @@ impl Data {
 
 		self.eventid_pduid.insert(pdu.event_id.as_bytes(), pdu_id);
 		self.eventid_outlierpdu.remove(pdu.event_id.as_bytes());
+		self.index_timestamp(pdu_id, &pdu.event_id, pdu.origin_server_ts.into());
+	}
+
+	/// Indexes the event by its room and origin_server_ts, see
+	/// [`Self::event_ids_by_timestamp`].
+	pub(super) fn index_timestamp(&self, pdu_id: &RawPduId, event_id: &EventId, ts: u64) {
+		let PduId { shortroomid, shorteventid } = (*pdu_id).into();
+		let key = timestamp_key(shortroomid, ts, shorteventid.into_unsigned());
+		self.roomid_ts_eventid.insert(&key, event_id);
+	}
+
+	/// Returns the room's event ids ordered by origin_server_ts, starting with
+	/// the events sent at `ts` and moving in `dir`.
+	pub(super) fn event_ids_by_timestamp(
+		&self,
+		shortroomid: ShortRoomId,
+		ts: u64,
+		dir: Direction,
+	) -> impl Stream<Item = OwnedEventId> + Send + '_ {
+		let from = timestamp_seek(shortroomid, ts, dir);
+		match dir {
+			| Direction::Forward => self.roomid_ts_eventid.raw_stream_from(&from).boxed(),
+			| Direction::Backward => self.roomid_ts_eventid.rev_raw_stream_from(&from).boxed(),
+		}
+		.ignore_err()
+		.ready_take_while(move |(key, _)| key.starts_with(&shortroomid.to_be_bytes()))
+		.ready_filter_map(|(_, event_id)| str_from_bytes(event_id).ok()?.try_into().ok())
 	}
 
 	pub(super) fn prepend_backfill_pdu(
@@ -230,7 +281,7 @@ impl Data {
 		user_id: Option<&'a UserId>,
 		room_id: &'a RoomId,
 		until: PduCount,
-	) -> Result<impl Stream<Item = PdusIterItem> + Send + 'a> {
+	) -> Result<impl Stream<Item = Result<PdusIterItem>> + Send + 'a> {
 		let current = self
 			.count_to_id(room_id, until, Direction::Backward)
 			.await?;
@@ -250,7 +301,7 @@ impl Data {
 		user_id: Option<&'a UserId>,
 		room_id: &'a RoomId,
 		from: PduCount,
-	) -> Result<impl Stream<Item = PdusIterItem> + Send + Unpin + 'a> {
+	) -> Result<impl Stream<Item = Result<PdusIterItem>> + Send + Unpin + 'a> {
 		let current = self.count_to_id(room_id, from, Direction::Forward).await?;
 		let prefix = current.shortroomid();
 		let stream = self
@@ -263,11 +314,15 @@ impl Data {
 		Ok(stream)
 	}
 
-	fn each_pdu((pdu_id, pdu): KeyVal<'_>, user_id: Option<&UserId>) -> PdusIterItem {
+	pub(super) fn each_pdu(
+		(pdu_id, pdu): KeyVal<'_>,
+		user_id: Option<&UserId>,
+	) -> Result<PdusIterItem> {
 		let pdu_id: RawPduId = pdu_id.into();
 
-		let mut pdu = serde_json::from_slice::<PduEvent>(pdu)
-			.expect("PduEvent in pduid_pdu database column is invalid JSON");
+		let mut pdu = serde_json::from_slice::<PduEvent>(pdu).map_err(|e| {
+			err!(Database("PduEvent {pdu_id:?} in pduid_pdu is invalid JSON: {e}"))
+		})?;
 
 		if Some(pdu.sender.borrow()) != user_id {
 			pdu.remove_transaction_id().log_err().ok();
@@ -275,7 +330,7 @@ impl Data {
 
 		pdu.add_age().log_err().ok();
 
-		(pdu_id.pdu_count(), pdu)
+		Ok((pdu_id.pdu_count(), pdu))
 	}
 
 	pub(super) fn increment_notification_counts(
@@ -301,6 +356,33 @@ impl Data {
 		}
 	}
 
+	/// Removes event ids whose PDU is gone. Entries are removed one at a time
+	/// so this can run alongside normal reads and writes.
+	pub(super) async fn gc(&self) -> GcReport {
+		let mut report = GcReport::default();
+
+		// The PDU is always written before its event id, so a missing PDU here
+		// is not a write in progress.
+		let dangling: Vec<Vec<u8>> = self
+			.eventid_pduid
+			.raw_stream()
+			.ignore_err()
+			.filter_map(|(event_id, pdu_id)| async move {
+				is_dangling(&self.pduid_pdu.get(pdu_id).await).then(|| event_id.to_vec())
+			})
+			.collect()
+			.await;
+
+		for event_id in &dangling {
+			self.eventid_pduid.remove(event_id);
+			report.dangling_event_ids = report.dangling_event_ids.saturating_add(1);
+		}
+
+		self.lasttimelinecount_cache.lock().await.clear();
+
+		report
+	}
+
 	async fn count_to_id(
 		&self,
 		room_id: &RoomId,
@@ -324,6 +406,30 @@ impl Data {
 	}
 }
 
+/// Whether the lookup of an event id's PDU shows the PDU is gone; read
+/// errors other than a missing entry never count as dangling.
+/// Builds the [`TimestampKey`] of an event.
+pub(super) fn timestamp_key(shortroomid: ShortRoomId, ts: u64, count: u64) -> TimestampKey {
+	let mut key = TimestampKey::new();
+	key.extend(shortroomid.to_be_bytes());
+	key.extend(ts.to_be_bytes());
+	key.extend(count.to_be_bytes());
+	key
+}
+
+/// Where [`Data::event_ids_by_timestamp`] starts: before the first event sent
+/// at `ts` going forward, or after the last one going backward.
+pub(super) fn timestamp_seek(shortroomid: ShortRoomId, ts: u64, dir: Direction) -> TimestampKey {
+	let count = match dir {
+		| Direction::Forward => u64::MIN,
+		| Direction::Backward => u64::MAX,
+	};
+
+	timestamp_key(shortroomid, ts, count)
+}
+
+pub(super) fn is_dangling<T>(pdu: &Result<T>) -> bool { pdu.is_not_found() }
+
 //TODO: this is an ABA
 fn increment(db: &Arc<Map>, key: &[u8]) {
 	let old = db.get_blocking(key);
@@ -1,4 +1,5 @@
 mod data;
+mod tests;
 
 use std::{
 	cmp,
@@ -6,12 +7,17 @@ use std::{
 	fmt::Write,
 	iter::once,
 	sync::Arc,
+	time::Instant,
 };
 
 use conduwuit::{
 	debug, debug_warn, err, error, implement, info,
 	pdu::{EventHash, PduBuilder, PduCount, PduEvent},
-	utils::{self, stream::TryIgnore, IterStream, MutexMap, MutexMapGuard, ReadyExt},
+	utils::{
+		self,
+		stream::{TryIgnore, TryReadyExt},
+		IterStream, MutexMap, MutexMapGuard, ReadyExt,
+	},
 	validated, warn, Err, Error, Result, Server,
 };
 pub use conduwuit::{PduId, RawPduId};
@@ -19,7 +25,7 @@ use futures::{
 	future, future::ready, Future, FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt,
 };
 use ruma::{
-	api::federation,
+	api::{federation, Direction},
 	canonical_json::to_canonical_value,
 	events::{
 		push_rules::PushRulesEvent,
@@ -34,14 +40,16 @@ use ruma::{
 	},
 	push::{Action, Ruleset, Tweak},
 	state_res::{self, Event, RoomVersion},
-	uint, user_id, CanonicalJsonObject, CanonicalJsonValue, EventId, OwnedEventId, OwnedRoomId,
-	OwnedServerName, RoomId, RoomVersionId, ServerName, UserId,
+	uint, user_id, CanonicalJsonObject, CanonicalJsonValue, EventId, MilliSecondsSinceUnixEpoch,
+	OwnedEventId, OwnedRoomId, OwnedServerName, OwnedUserId, RoomId, RoomVersionId, ServerName,
+	UserId,
 };
 use serde::Deserialize;
 use serde_json::value::{to_raw_value, RawValue as RawJsonValue};
+use tracing::{field, Instrument, Span};
 
+pub use self::data::{GcReport, PdusIterItem};
 use self::data::Data;
-pub use self::data::PdusIterItem;
 use crate::{
 	account_data, admin, appservice,
 	appservice::NamespaceRegex,
@@ -174,6 +182,7 @@ impl Service {
 		self.all_pdus(user_id!("@doesntmatter:conduit.rs"), room_id)
 			.next()
 			.await
+			.transpose()?
 			.map(|(_, p)| Arc::new(p))
 			.ok_or_else(|| err!(Request(NotFound("No PDU found in room"))))
 	}
@@ -184,6 +193,7 @@ impl Service {
 			.await?
 			.next()
 			.await
+			.transpose()?
 			.map(|(_, p)| Arc::new(p))
 			.ok_or_else(|| err!(Request(NotFound("No PDU found in room"))))
 	}
@@ -282,6 +292,9 @@ impl Service {
 		self.db.get_pdu_json_from_id(pdu_id).await
 	}
 
+	/// Removes event ids whose PDU no longer exists from the timeline.
+	pub async fn gc(&self) -> GcReport { self.db.gc().await }
+
 	/// Removes a pdu and creates a new one with the same id.
 	#[tracing::instrument(skip(self), level = "debug")]
 	pub async fn replace_pdu(
@@ -299,14 +312,31 @@ impl Service {
 	/// happens in `append_pdu`.
 	///
 	/// Returns pdu id
-	#[tracing::instrument(skip_all)]
 	pub async fn append_pdu(
 		&self,
 		pdu: &PduEvent,
-		mut pdu_json: CanonicalJsonObject,
+		pdu_json: CanonicalJsonObject,
 		leaves: Vec<OwnedEventId>,
 		state_lock: &RoomMutexGuard, /* Take mutex guard to make sure users get the room state
 		                              * mutex */
+	) -> Result<RawPduId> {
+		let span = append_span(pdu);
+		let timer = Instant::now();
+		let pdu_id = self
+			.append_pdu_inner(pdu, pdu_json, leaves, state_lock)
+			.instrument(span.clone())
+			.await;
+
+		span.record("elapsed", field::debug(timer.elapsed()));
+		pdu_id
+	}
+
+	async fn append_pdu_inner(
+		&self,
+		pdu: &PduEvent,
+		mut pdu_json: CanonicalJsonObject,
+		leaves: Vec<OwnedEventId>,
+		state_lock: &RoomMutexGuard,
 	) -> Result<RawPduId> {
 		// Coalesce database writes for the remainder of this scope.
 		let _cork = self.db.db.cork_and_flush();
@@ -522,6 +552,12 @@ impl Service {
 					},
 				};
 			},
+			| TimelineEventType::RoomCreate =>
+				if self.services.globals.user_is_local(&pdu.sender) {
+					self.services
+						.state_cache
+						.mark_as_created(&pdu.sender, &pdu.room_id);
+				},
 			| TimelineEventType::SpaceChild =>
 				if let Some(_state_key) = &pdu.state_key {
 					self.services
@@ -563,7 +599,15 @@ impl Service {
 			| TimelineEventType::RoomMessage => {
 				let content: ExtractBody = pdu.get_content()?;
 				if let Some(body) = content.body {
-					self.services.search.index_pdu(shortroomid, &pdu_id, &body);
+					// Cleartext bodies in encrypted rooms are not indexed for search
+					if !self
+						.services
+						.state_accessor
+						.is_encrypted_room(&pdu.room_id)
+						.await
+					{
+						self.services.search.index_pdu(shortroomid, &pdu_id, &body);
+					}
 
 					if self.services.admin.is_admin_command(pdu, &body).await {
 						self.services
@@ -868,11 +912,17 @@ impl Service {
 		state_lock: &RoomMutexGuard, /* Take mutex guard to make sure users get the room state
 		                              * mutex */
 	) -> Result<Arc<EventId>> {
+		// The admin room stays writable so read-only mode can be turned off again.
+		let is_admin_room = self.services.admin.is_admin_room(room_id).await;
+		if !is_admin_room {
+			self.services.server.check_writable()?;
+		}
+
 		let (pdu, pdu_json) = self
 			.create_hash_and_sign_event(pdu_builder, sender, room_id, state_lock)
 			.await?;
 
-		if self.services.admin.is_admin_room(&pdu.room_id).await {
+		if is_admin_room {
 			self.check_pdu_for_admin_room(&pdu, sender).boxed().await?;
 		}
 
@@ -913,15 +963,12 @@ impl Service {
 		// fail.
 		let statehashid = self.services.state.append_to_state(&pdu).await?;
 
+		// This PDU replaces the leaves it references; any it did not reference (we
+		// only reference up to 20) remain leaves of the room
+		let leaves = self.services.state.forward_extremities_after(&pdu).await;
+
 		let pdu_id = self
-			.append_pdu(
-				&pdu,
-				pdu_json,
-				// Since this PDU references all pdu_leaves we can update the leaves
-				// of the room
-				vec![(*pdu.event_id).to_owned()],
-				state_lock,
-			)
+			.append_pdu(&pdu, pdu_json, leaves, state_lock)
 			.boxed()
 			.await?;
 
@@ -965,7 +1012,7 @@ impl Service {
 
 	/// Append the incoming event setting the state snapshot to the state from
 	/// the server that sent the event.
-	#[tracing::instrument(skip_all)]
+	#[tracing::instrument(skip_all, fields(room_id = %pdu.room_id, event_id = %pdu.event_id))]
 	pub async fn append_incoming_pdu(
 		&self,
 		pdu: &PduEvent,
@@ -1004,47 +1051,98 @@ impl Service {
 		Ok(Some(pdu_id))
 	}
 
-	/// Returns an iterator over all PDUs in a room. Unknown rooms produce no
-	/// items.
+	/// Returns an iterator over all PDUs in a room. Unknown rooms produce a
+	/// single error.
 	#[inline]
 	pub fn all_pdus<'a>(
 		&'a self,
 		user_id: &'a UserId,
 		room_id: &'a RoomId,
-	) -> impl Stream<Item = PdusIterItem> + Send + Unpin + 'a {
+	) -> impl Stream<Item = Result<PdusIterItem>> + Send + Unpin + 'a {
 		self.pdus(Some(user_id), room_id, None)
-			.map_ok(|stream| stream.map(Ok))
 			.try_flatten_stream()
-			.ignore_err()
 			.boxed()
 	}
 
 	/// Reverse iteration starting at from.
+	///
+	/// Counts come from the server-wide counter shared by every room and by
+	/// other data, so a room's counts have gaps but always increase. PDU ids are
+	/// the room's short id followed by the big-endian count, which keeps a
+	/// room's events contiguous and in order; backfilled events have negative
+	/// counts and sort before all others. The bound itself is not returned and
+	/// no limit is applied; callers take as many items as they need. A PDU
+	/// which cannot be read is returned as a database error in its place.
 	#[tracing::instrument(skip(self), level = "debug")]
 	pub async fn pdus_rev<'a>(
 		&'a self,
 		user_id: Option<&'a UserId>,
 		room_id: &'a RoomId,
 		until: Option<PduCount>,
-	) -> Result<impl Stream<Item = PdusIterItem> + Send + 'a> {
+	) -> Result<impl Stream<Item = Result<PdusIterItem>> + Send + 'a> {
 		self.db
 			.pdus_rev(user_id, room_id, until.unwrap_or_else(PduCount::max))
 			.await
 	}
 
-	/// Forward iteration starting at from.
+	/// Forward iteration starting at from. Ordering and bounds are as for
+	/// [`Self::pdus_rev`].
 	#[tracing::instrument(skip(self), level = "debug")]
 	pub async fn pdus<'a>(
 		&'a self,
 		user_id: Option<&'a UserId>,
 		room_id: &'a RoomId,
 		from: Option<PduCount>,
-	) -> Result<impl Stream<Item = PdusIterItem> + Send + 'a> {
+	) -> Result<impl Stream<Item = Result<PdusIterItem>> + Send + 'a> {
 		self.db
 			.pdus(user_id, room_id, from.unwrap_or_else(PduCount::min))
 			.await
 	}
 
+	/// Membership transitions in a room after `since`, in timeline order. Each
+	/// item carries the affected user, their new membership and the count of
+	/// the member event which made the change, usable as the next `since`.
+	pub async fn membership_changes_since<'a>(
+		&'a self,
+		room_id: &'a RoomId,
+		since: PduCount,
+	) -> Result<impl Stream<Item = Result<(OwnedUserId, MembershipState, PduCount)>> + Send + 'a>
+	{
+		let stream = self
+			.pdus(None, room_id, Some(since))
+			.await?
+			.ready_try_filter_map(|item| Ok(membership_change(item)));
+
+		Ok(stream)
+	}
+
+	/// Finds the event nearest to `ts`: the latest event sent at or before it
+	/// when going backward, or the earliest event sent at or after it when
+	/// going forward.
+	#[tracing::instrument(skip(self), level = "debug")]
+	pub async fn event_at_timestamp(
+		&self,
+		room_id: &RoomId,
+		ts: MilliSecondsSinceUnixEpoch,
+		dir: &Direction,
+	) -> Result<PduEvent> {
+		let shortroomid = self.services.short.get_shortroomid(room_id).await?;
+
+		self.db
+			.event_ids_by_timestamp(shortroomid, ts.get().into(), *dir)
+			.filter_map(|event_id| async move { self.get_non_outlier_pdu(&event_id).await.ok() })
+			.boxed()
+			.next()
+			.await
+			.ok_or_else(|| err!(Request(NotFound("No event found in {room_id} near {ts:?}"))))
+	}
+
+	/// Indexes an event already in the timeline for
+	/// [`Self::event_at_timestamp`]. New events are indexed when appended.
+	pub fn index_timestamp(&self, pdu_id: &RawPduId, event_id: &EventId, ts: u64) {
+		self.db.index_timestamp(pdu_id, event_id, ts);
+	}
+
 	/// Replace a PDU with the redacted form.
 	#[tracing::instrument(skip(self, reason))]
 	pub async fn redact_pdu(
@@ -1053,6 +1151,35 @@ impl Service {
 		reason: &PduEvent,
 		shortroomid: ShortRoomId,
 	) -> Result {
+		self.redact_pdu_with(event_id, shortroomid, |pdu, room_version_id| {
+			pdu.redact(room_version_id, reason)
+		})
+		.await
+	}
+
+	/// Replace a PDU with the redacted form without a redaction event, e.g.
+	/// when it outlives the room's retention policy.
+	#[tracing::instrument(skip(self))]
+	pub async fn expire_pdu(
+		&self,
+		event_id: &EventId,
+		shortroomid: ShortRoomId,
+		_state_lock: &RoomMutexGuard, /* Take mutex guard to make sure users get the room
+		                               * state mutex */
+	) -> Result {
+		self.redact_pdu_with(event_id, shortroomid, PduEvent::redact_content)
+			.await
+	}
+
+	async fn redact_pdu_with<F>(
+		&self,
+		event_id: &EventId,
+		shortroomid: ShortRoomId,
+		redact: F,
+	) -> Result
+	where
+		F: FnOnce(&mut PduEvent, &RoomVersionId) -> Result + Send,
+	{
 		// TODO: Don't reserialize, keep original json
 		let Ok(pdu_id) = self.get_pdu_id(event_id).await else {
 			// If event does not exist, just noop
@@ -1073,7 +1200,7 @@ impl Service {
 
 		let room_version_id = self.services.state.get_room_version(&pdu.room_id).await?;
 
-		pdu.redact(&room_version_id, reason)?;
+		redact(&mut pdu, &room_version_id)?;
 
 		let obj = utils::to_canonical_object(&pdu).map_err(|e| {
 			err!(Database(error!(?event_id, ?e, "Failed to convert PDU to canonical JSON")))
@@ -1104,7 +1231,7 @@ impl Service {
 			.all_pdus(user_id!("@doesntmatter:conduit.rs"), room_id)
 			.next()
 			.await
-			.expect("Room is not empty");
+			.expect("Room is not empty")?;
 
 		if first_pdu.0 < from {
 			// No backfill required, there are still events between them
@@ -1243,13 +1370,17 @@ impl Service {
 
 		// Insert pdu
 		self.db.prepend_backfill_pdu(&pdu_id, &event_id, &value);
+		self.db
+			.index_timestamp(&pdu_id, &event_id, pdu.origin_server_ts.into());
 
 		drop(insert_lock);
 
 		if pdu.kind == TimelineEventType::RoomMessage {
 			let content: ExtractBody = pdu.get_content()?;
 			if let Some(body) = content.body {
-				self.services.search.index_pdu(shortroomid, &pdu_id, &body);
+				if !self.services.state_accessor.is_encrypted_room(&room_id).await {
+					self.services.search.index_pdu(shortroomid, &pdu_id, &body);
+				}
 			}
 		}
 		drop(mutex_lock);
@@ -1331,3 +1462,29 @@ async fn check_pdu_for_admin_room(&self, pdu: &PduEvent, sender: &UserId) -> Res
 
 	Ok(())
 }
+
+/// The span `pdu` is appended to the timeline in. It records the room and
+/// event ids, and how long the append took once it finishes.
+fn append_span(pdu: &PduEvent) -> Span {
+	tracing::info_span!(
+		"append_pdu",
+		room_id = %pdu.room_id,
+		event_id = %pdu.event_id,
+		elapsed = field::Empty,
+	)
+}
+
+/// The membership transition made by a timeline event, if it is a member
+/// event.
+fn membership_change(
+	(count, pdu): PdusIterItem,
+) -> Option<(OwnedUserId, MembershipState, PduCount)> {
+	if pdu.kind != TimelineEventType::RoomMember {
+		return None;
+	}
+
+	let user_id = UserId::parse(pdu.state_key.as_deref()?).ok()?;
+	let content: RoomMemberEventContent = pdu.get_content().ok()?;
+
+	Some((user_id, content.membership, count))
+}
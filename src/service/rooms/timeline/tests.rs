@@ -0,0 +1,219 @@
+#![cfg(test)]
+
+use std::{
+	collections::BTreeMap,
+	fmt,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use conduwuit::{err, Error, PduCount, Result};
+use ruma::{api::Direction, events::room::member::MembershipState, owned_user_id};
+use serde_json::json;
+use tracing::{
+	field::{self, Field, Visit},
+	span::{Attributes, Id, Record},
+	Event, Metadata, Subscriber,
+};
+
+use super::{
+	append_span,
+	data::{is_dangling, timestamp_key, timestamp_seek, Data},
+	membership_change, PduId, RawPduId,
+};
+use crate::rooms::tests::pdu;
+
+/// Seeks like `Data::event_ids_by_timestamp` over an index holding
+/// `timeline` for room 1 and a few events of room 2.
+fn nearest(
+	timeline: &[(u64, &'static str)],
+	target: u64,
+	dir: Direction,
+) -> Option<&'static str> {
+	let mut index = BTreeMap::new();
+	for (count, &(ts, event_id)) in (1..).zip(timeline) {
+		index.insert(timestamp_key(1, ts, count), event_id);
+	}
+
+	for (count, ts) in (1..).zip([0, 250, 1_000]) {
+		index.insert(timestamp_key(2, ts, count), "$other_room");
+	}
+
+	let from = timestamp_seek(1, target, dir);
+	let mut entries: Box<dyn Iterator<Item = _>> = match dir {
+		| Direction::Forward => Box::new(index.range(from..)),
+		| Direction::Backward => Box::new(index.range(..=from).rev()),
+	};
+
+	entries
+		.next()
+		.filter(|(key, _)| key.starts_with(&1_u64.to_be_bytes()))
+		.map(|(_, &event_id)| event_id)
+}
+
+const TIMELINE: &[(u64, &str)] = &[(100, "$a"), (200, "$b"), (300, "$c"), (400, "$d")];
+
+#[test]
+fn timestamp_backward() {
+	assert_eq!(nearest(TIMELINE, 250, Direction::Backward), Some("$b"));
+	assert_eq!(nearest(TIMELINE, 300, Direction::Backward), Some("$c"));
+	assert_eq!(nearest(TIMELINE, 500, Direction::Backward), Some("$d"));
+	assert_eq!(nearest(TIMELINE, 50, Direction::Backward), None);
+}
+
+#[test]
+fn timestamp_forward() {
+	assert_eq!(nearest(TIMELINE, 250, Direction::Forward), Some("$c"));
+	assert_eq!(nearest(TIMELINE, 300, Direction::Forward), Some("$c"));
+	assert_eq!(nearest(TIMELINE, 50, Direction::Forward), Some("$a"));
+	assert_eq!(nearest(TIMELINE, 500, Direction::Forward), None);
+}
+
+#[test]
+fn timestamp_skewed_event() {
+	// $skewed claims an early timestamp but arrived late; the index orders it
+	// by its timestamp rather than by arrival
+	let timeline = &[(100, "$a"), (200, "$b"), (300, "$c"), (50, "$skewed"), (400, "$d")];
+
+	assert_eq!(nearest(timeline, 250, Direction::Forward), Some("$c"));
+	assert_eq!(nearest(timeline, 150, Direction::Forward), Some("$b"));
+	assert_eq!(nearest(timeline, 250, Direction::Backward), Some("$b"));
+	assert_eq!(nearest(timeline, 75, Direction::Backward), Some("$skewed"));
+}
+
+#[test]
+fn timestamp_ties() {
+	let timeline = &[(100, "$a"), (200, "$b"), (200, "$c"), (300, "$d")];
+
+	assert_eq!(nearest(timeline, 200, Direction::Backward), Some("$c"));
+	assert_eq!(nearest(timeline, 200, Direction::Forward), Some("$b"));
+}
+
+#[test]
+fn timestamp_long_history() {
+	// far more events than a walk from the end of the timeline would visit
+	let mut timeline: Vec<_> = (0..50_000_u64)
+		.map(|i| (i.saturating_mul(10), "$event"))
+		.collect();
+	timeline[1].1 = "$early";
+
+	assert_eq!(nearest(&timeline, 15, Direction::Backward), Some("$early"));
+	assert_eq!(nearest(&timeline, 5, Direction::Forward), Some("$early"));
+}
+
+#[test]
+fn membership_join_then_leave() {
+	let member = |membership: &str| {
+		pdu(json!({
+			"type": "m.room.member",
+			"state_key": "@bob:example.org",
+			"sender": "@bob:example.org",
+			"content": { "membership": membership },
+		}))
+	};
+	let message = pdu(json!({ "content": { "msgtype": "m.text", "body": "hi" } }));
+
+	let timeline = vec![
+		(PduCount::Normal(1), member("join")),
+		(PduCount::Normal(2), message),
+		(PduCount::Normal(3), member("leave")),
+	];
+
+	let changes: Vec<_> = timeline.into_iter().filter_map(membership_change).collect();
+
+	assert_eq!(changes, vec![
+		(owned_user_id!("@bob:example.org"), MembershipState::Join, PduCount::Normal(1)),
+		(owned_user_id!("@bob:example.org"), MembershipState::Leave, PduCount::Normal(3)),
+	]);
+}
+
+#[test]
+fn gc_only_missing_pdus_are_dangling() {
+	let found: Result<()> = Ok(());
+	let missing: Result<()> = Err(err!(Request(NotFound("Not found in database"))));
+	let failed: Result<()> = Err(err!(Database("I/O error")));
+
+	assert!(!is_dangling(&found));
+	assert!(is_dangling(&missing));
+	assert!(!is_dangling(&failed));
+}
+
+#[test]
+fn each_pdu_reports_unreadable_pdus() {
+	let pdu_id: RawPduId = PduId {
+		shortroomid: 1,
+		shorteventid: PduCount::Normal(7),
+	}
+	.into();
+
+	let stored = serde_json::to_vec(&pdu(json!({}))).expect("valid PDU");
+	let (count, _) = Data::each_pdu((pdu_id.as_bytes(), stored.as_slice()), None)
+		.expect("stored PDU is readable");
+	assert_eq!(count, PduCount::Normal(7));
+
+	let corrupt = Data::each_pdu((pdu_id.as_bytes(), br#"{"event_id":"#.as_slice()), None);
+	assert!(matches!(corrupt, Err(Error::Database(_))));
+}
+
+/// Span names and the fields recorded on them, by span id.
+#[derive(Clone, Default)]
+struct SpanCapture(Arc<Mutex<BTreeMap<u64, (&'static str, Fields)>>>);
+
+#[derive(Default)]
+struct Fields(BTreeMap<&'static str, String>);
+
+impl Subscriber for SpanCapture {
+	fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+
+	fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+		let mut fields = Fields::default();
+		attrs.record(&mut fields);
+
+		let mut spans = self.0.lock().expect("locked");
+		let id = u64::try_from(spans.len())
+			.expect("span count fits")
+			.saturating_add(1);
+		spans.insert(id, (attrs.metadata().name(), fields));
+
+		Id::from_u64(id)
+	}
+
+	fn record(&self, span: &Id, values: &Record<'_>) {
+		let mut spans = self.0.lock().expect("locked");
+		if let Some((_, fields)) = spans.get_mut(&span.into_u64()) {
+			values.record(fields);
+		}
+	}
+
+	fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+	fn event(&self, _: &Event<'_>) {}
+
+	fn enter(&self, _: &Id) {}
+
+	fn exit(&self, _: &Id) {}
+}
+
+impl Visit for Fields {
+	fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+		self.0.insert(field.name(), format!("{value:?}"));
+	}
+}
+
+#[test]
+fn append_span_records_room_id() {
+	let capture = SpanCapture::default();
+	let pdu = pdu(json!({}));
+
+	tracing::subscriber::with_default(capture.clone(), || {
+		let span = append_span(&pdu);
+		span.record("elapsed", field::debug(Duration::from_millis(5)));
+	});
+
+	let spans = capture.0.lock().expect("locked");
+	let (name, fields) = spans.get(&1).expect("span was created");
+	assert_eq!(*name, "append_pdu");
+	assert_eq!(fields.0["room_id"], "!room:example.org");
+	assert_eq!(fields.0["event_id"], "$event:example.org");
+	assert_eq!(fields.0["elapsed"], "5ms");
+}
@@ -1,3 +1,5 @@
+mod tests;
+
 use std::{collections::BTreeMap, sync::Arc};
 
 use conduwuit::{
@@ -124,19 +126,13 @@ impl Service {
 	/// Makes sure that typing events with old timestamps get removed.
 	async fn typings_maintain(&self, room_id: &RoomId) -> Result<()> {
 		let current_timestamp = utils::millis_since_unix_epoch();
-		let mut removable = Vec::new();
-
-		{
+		let removable = {
 			let typing = self.typing.read().await;
 			let Some(room) = typing.get(room_id) else {
 				return Ok(());
 			};
 
-			for (user, timeout) in room {
-				if *timeout < current_timestamp {
-					removable.push(user.clone());
-				}
-			}
+			expired_typers(room, current_timestamp)
 		};
 
 		if !removable.is_empty() {
@@ -238,3 +234,12 @@ impl Service {
 		Ok(())
 	}
 }
+
+/// Users in a room whose typing timeout has been reached at `now`. A timeout
+/// is the first millisecond at which the user is no longer typing.
+fn expired_typers(room: &BTreeMap<OwnedUserId, u64>, now: u64) -> Vec<OwnedUserId> {
+	room.iter()
+		.filter(|(_, &timeout)| timeout <= now)
+		.map(|(user, _)| user.clone())
+		.collect()
+}
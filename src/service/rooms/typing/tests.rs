@@ -0,0 +1,26 @@
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+
+use ruma::owned_user_id;
+
+use super::expired_typers;
+
+#[test]
+fn typing_expires_at_deadline() {
+	let user = owned_user_id!("@alice:example.org");
+	let room = BTreeMap::from([(user.clone(), 1_000)]);
+
+	assert!(expired_typers(&room, 999).is_empty());
+	assert_eq!(expired_typers(&room, 1_000), vec![user.clone()]);
+	assert_eq!(expired_typers(&room, 1_001), vec![user]);
+}
+
+#[test]
+fn typing_expiry_is_per_user() {
+	let alice = owned_user_id!("@alice:example.org");
+	let bob = owned_user_id!("@bob:example.org");
+	let room = BTreeMap::from([(alice.clone(), 1_000), (bob, 2_000)]);
+
+	assert_eq!(expired_typers(&room, 1_500), vec![alice]);
+}
@@ -38,12 +38,7 @@ impl super::Service {
 			return Err!(Config("allow_federation", "Federation is disabled."));
 		}
 
-		if self
-			.server
-			.config
-			.forbidden_remote_server_names
-			.contains(dest)
-		{
+		if !self.server.config.federation_allowed(dest) {
 			return Err!(Request(Forbidden(debug_warn!(
 				"Federation with {dest} is not allowed."
 			))));
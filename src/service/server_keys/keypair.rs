@@ -1,25 +1,87 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, mem, sync::Arc};
 
 use conduwuit::{debug, debug_info, err, error, utils, utils::string_from_bytes, Result};
 use database::Database;
-use ruma::{api::federation::discovery::VerifyKey, serde::Base64, signatures::Ed25519KeyPair};
+use ruma::{
+	api::federation::discovery::VerifyKey, serde::Base64, signatures::Ed25519KeyPair,
+	OwnedServerSigningKeyId, ServerSigningKeyId,
+};
 
 use super::VerifyKeys;
 
-pub(super) fn init(db: &Arc<Database>) -> Result<(Box<Ed25519KeyPair>, VerifyKeys)> {
+/// Our keypairs by key id. The newest signs everything we send; the others
+/// were replaced while running and are only kept to publish their public key.
+pub(super) struct ActiveKeys {
+	newest: OwnedServerSigningKeyId,
+	keypairs: BTreeMap<OwnedServerSigningKeyId, Arc<Ed25519KeyPair>>,
+}
+
+impl ActiveKeys {
+	pub(super) fn new(keypair: Box<Ed25519KeyPair>) -> Result<Self> {
+		let newest = key_id(&keypair)?;
+		let keypairs = BTreeMap::from([(newest.clone(), Arc::from(keypair))]);
+
+		Ok(Self { newest, keypairs })
+	}
+
+	/// Makes `keypair` the newest key, returning the id and public key of the
+	/// key it replaces.
+	pub(super) fn insert(
+		&mut self,
+		keypair: Box<Ed25519KeyPair>,
+	) -> Result<(OwnedServerSigningKeyId, VerifyKey)> {
+		let key_id = key_id(&keypair)?;
+		self.keypairs.insert(key_id.clone(), keypair.into());
+
+		let replaced = mem::replace(&mut self.newest, key_id);
+		let verify_key = verify_key(&self.keypairs[&replaced]);
+
+		Ok((replaced, verify_key))
+	}
+
+	#[inline]
+	pub(super) fn newest(&self) -> (&ServerSigningKeyId, &Arc<Ed25519KeyPair>) {
+		let keypair = self
+			.keypairs
+			.get(&self.newest)
+			.expect("newest keypair is present");
+
+		(&self.newest, keypair)
+	}
+
+	pub(super) fn verify_keys(&self) -> VerifyKeys {
+		self.keypairs
+			.iter()
+			.map(|(key_id, keypair)| (key_id.clone(), verify_key(keypair)))
+			.collect()
+	}
+}
+
+pub(super) fn init(db: &Arc<Database>) -> Result<ActiveKeys> {
 	let keypair = load(db).inspect_err(|_e| {
 		error!("Keypair invalid. Deleting...");
 		remove(db);
 	})?;
 
-	let verify_key = VerifyKey {
+	ActiveKeys::new(keypair)
+}
+
+/// Generates a keypair which replaces the stored one, so it is also loaded
+/// on the next startup.
+pub(super) fn generate(db: &Arc<Database>) -> Result<Box<Ed25519KeyPair>> {
+	let (version, key) = create(db)?;
+	from_der(&key, version)
+}
+
+pub(super) fn verify_key(keypair: &Ed25519KeyPair) -> VerifyKey {
+	VerifyKey {
 		key: Base64::new(keypair.public_key().to_vec()),
-	};
+	}
+}
 
+fn key_id(keypair: &Ed25519KeyPair) -> Result<OwnedServerSigningKeyId> {
 	let id = format!("ed25519:{}", keypair.version());
-	let verify_keys: VerifyKeys = [(id.try_into()?, verify_key)].into();
-
-	Ok((keypair, verify_keys))
+	Ok(id.try_into()?)
 }
 
 fn load(db: &Arc<Database>) -> Result<Box<Ed25519KeyPair>> {
@@ -39,7 +101,11 @@ fn load(db: &Arc<Database>) -> Result<Box<Ed25519KeyPair>> {
 			create(db)
 		})?;
 
-	let key = Ed25519KeyPair::from_der(&key, version)
+	from_der(&key, version)
+}
+
+fn from_der(key: &[u8], version: String) -> Result<Box<Ed25519KeyPair>> {
+	let key = Ed25519KeyPair::from_der(key, version)
 		.map_err(|e| err!("Failed to load ed25519 keypair from der: {e:?}"))?;
 
 	Ok(Box::new(key))
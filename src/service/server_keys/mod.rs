@@ -3,19 +3,24 @@ mod get;
 mod keypair;
 mod request;
 mod sign;
+mod tests;
 mod verify;
 
-use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use std::{
+	collections::BTreeMap,
+	sync::{Arc, RwLock},
+	time::Duration,
+};
 
 use conduwuit::{
 	implement,
 	utils::{timepoint_from_now, IterStream},
 	Result, Server,
 };
-use database::{Deserialized, Json, Map};
+use database::{Database, Deserialized, Json, Map};
 use futures::StreamExt;
 use ruma::{
-	api::federation::discovery::{ServerSigningKeys, VerifyKey},
+	api::federation::discovery::{OldVerifyKey, ServerSigningKeys, VerifyKey},
 	serde::Raw,
 	signatures::{Ed25519KeyPair, PublicKeyMap, PublicKeySet},
 	CanonicalJsonObject, MilliSecondsSinceUnixEpoch, OwnedServerSigningKeyId, RoomVersionId,
@@ -23,17 +28,18 @@ use ruma::{
 };
 use serde_json::value::RawValue as RawJsonValue;
 
+use self::keypair::ActiveKeys;
 use crate::{globals, sending, Dep};
 
 pub struct Service {
-	keypair: Box<Ed25519KeyPair>,
-	verify_keys: VerifyKeys,
+	keys: RwLock<ActiveKeys>,
 	minimum_valid: Duration,
 	services: Services,
 	db: Data,
 }
 
 struct Services {
+	db: Arc<Database>,
 	globals: Dep<globals::Service>,
 	sending: Dep<sending::Service>,
 	server: Arc<Server>,
@@ -51,14 +57,13 @@ impl crate::Service for Service {
 	fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
 		let minimum_valid = Duration::from_secs(3600);
 
-		let (keypair, verify_keys) = keypair::init(args.db)?;
-		debug_assert!(verify_keys.len() == 1, "only one active verify_key supported");
+		let keys = keypair::init(args.db)?;
 
 		Ok(Arc::new(Self {
-			keypair,
-			verify_keys,
+			keys: RwLock::new(keys),
 			minimum_valid,
 			services: Services {
+				db: args.db.clone(),
 				globals: args.depend::<globals::Service>("globals"),
 				sending: args.depend::<sending::Service>("sending"),
 				server: args.server.clone(),
@@ -74,21 +79,51 @@ impl crate::Service for Service {
 
 #[implement(Service)]
 #[inline]
-pub fn keypair(&self) -> &Ed25519KeyPair { &self.keypair }
+pub fn keypair(&self) -> Arc<Ed25519KeyPair> {
+	let keys = self.keys.read().expect("locked for reading");
+	keys.newest().1.clone()
+}
 
 #[implement(Service)]
 #[inline]
-pub fn active_key_id(&self) -> &ServerSigningKeyId { self.active_verify_key().0 }
+pub fn active_key_id(&self) -> OwnedServerSigningKeyId {
+	let keys = self.keys.read().expect("locked for reading");
+	keys.newest().0.to_owned()
+}
 
 #[implement(Service)]
-#[inline]
-pub fn active_verify_key(&self) -> (&ServerSigningKeyId, &VerifyKey) {
-	debug_assert!(self.verify_keys.len() <= 1, "more than one active verify_key");
-	self.verify_keys
-		.iter()
-		.next()
-		.map(|(id, key)| (id.as_ref(), key))
-		.expect("missing active verify_key")
+pub fn active_verify_key(&self) -> (OwnedServerSigningKeyId, VerifyKey) {
+	let keys = self.keys.read().expect("locked for reading");
+	let (key_id, keypair) = keys.newest();
+
+	(key_id.to_owned(), keypair::verify_key(keypair))
+}
+
+/// Replaces the active signing key with a newly generated one, which signs
+/// everything from then on. The replaced key is only then published in
+/// `old_verify_keys`, so events it signed remain verifiable. Returns the ids of
+/// the replaced and the new key.
+#[implement(Service)]
+pub async fn rotate_signing_key(
+	&self,
+) -> Result<(OwnedServerSigningKeyId, OwnedServerSigningKeyId)> {
+	let keypair = keypair::generate(&self.services.db)?;
+	let ((old_key_id, old_verify_key), new_key_id) = {
+		let mut keys = self.keys.write().expect("locked for writing");
+		let replaced = keys.insert(keypair)?;
+
+		(replaced, keys.newest().0.to_owned())
+	};
+
+	let now = MilliSecondsSinceUnixEpoch::now();
+	let server_name = self.services.globals.server_name();
+	let mut keys = ServerSigningKeys::new(server_name.to_owned(), now);
+	keys.old_verify_keys
+		.insert(old_key_id.clone(), OldVerifyKey::new(now, old_verify_key.key));
+
+	self.add_signing_keys(keys).await;
+
+	Ok((old_key_id, new_key_id))
 }
 
 #[implement(Service)]
@@ -170,7 +205,8 @@ pub async fn verify_keys_for(&self, origin: &ServerName) -> VerifyKeys {
 		.unwrap_or(BTreeMap::new());
 
 	if self.services.globals.server_is_ours(origin) {
-		keys.extend(self.verify_keys.clone().into_iter());
+		let active = self.keys.read().expect("locked for reading");
+		keys.extend(active.verify_keys());
 	}
 
 	keys
@@ -6,7 +6,7 @@ pub fn sign_json(&self, object: &mut CanonicalJsonObject) -> Result {
 	use ruma::signatures::sign_json;
 
 	let server_name = self.services.globals.server_name().as_str();
-	sign_json(server_name, self.keypair(), object).map_err(Into::into)
+	sign_json(server_name, &*self.keypair(), object).map_err(Into::into)
 }
 
 #[implement(super::Service)]
@@ -18,5 +18,5 @@ pub fn hash_and_sign_event(
 	use ruma::signatures::hash_and_sign_event;
 
 	let server_name = self.services.globals.server_name().as_str();
-	hash_and_sign_event(server_name, self.keypair(), object, room_version).map_err(Into::into)
+	hash_and_sign_event(server_name, &*self.keypair(), object, room_version).map_err(Into::into)
 }
@@ -0,0 +1,75 @@
+#![cfg(test)]
+
+use ruma::{
+	signatures::{sign_json, verify_json, Ed25519KeyPair, PublicKeyMap},
+	CanonicalJsonObject, CanonicalJsonValue,
+};
+use serde_json::json;
+
+use super::keypair::ActiveKeys;
+
+fn keypair(version: &str) -> Box<Ed25519KeyPair> {
+	let der = Ed25519KeyPair::generate().expect("generated keypair");
+	let keypair = Ed25519KeyPair::from_der(&der, version.to_owned()).expect("valid keypair");
+
+	Box::new(keypair)
+}
+
+/// Signs an object the way sign_json() does, with the newest key.
+fn sign(keys: &ActiveKeys) -> CanonicalJsonObject {
+	let mut object = serde_json::from_value(json!({ "content": "hello" })).expect("object");
+	sign_json("example.org", &**keys.newest().1, &mut object).expect("signed");
+
+	object
+}
+
+fn signing_key_ids(object: &CanonicalJsonObject) -> Vec<&str> {
+	let Some(CanonicalJsonValue::Object(signatures)) = object.get("signatures") else {
+		panic!("object is not signed");
+	};
+
+	let Some(CanonicalJsonValue::Object(ours)) = signatures.get("example.org") else {
+		panic!("object is not signed by us");
+	};
+
+	ours.keys().map(String::as_str).collect()
+}
+
+#[test]
+fn rotation_signs_with_new_key() {
+	let mut keys = ActiveKeys::new(keypair("old")).expect("valid key id");
+	let before = sign(&keys);
+
+	let (replaced, _) = keys.insert(keypair("new")).expect("valid key id");
+	let after = sign(&keys);
+
+	assert_eq!(replaced.as_str(), "ed25519:old");
+	assert_eq!(keys.newest().0.as_str(), "ed25519:new");
+	assert_eq!(signing_key_ids(&before), ["ed25519:old"]);
+	assert_eq!(signing_key_ids(&after), ["ed25519:new"]);
+}
+
+#[test]
+fn rotation_keeps_old_key_verifiable() {
+	let mut keys = ActiveKeys::new(keypair("old")).expect("valid key id");
+	let before = sign(&keys);
+
+	keys.insert(keypair("new")).expect("valid key id");
+	let after = sign(&keys);
+
+	let verify_keys = keys.verify_keys();
+	let key_ids: Vec<_> = verify_keys.keys().map(|key_id| key_id.as_str()).collect();
+	assert_eq!(key_ids, ["ed25519:new", "ed25519:old"]);
+
+	let public_keys: PublicKeyMap = [(
+		"example.org".to_owned(),
+		verify_keys
+			.iter()
+			.map(|(key_id, verify_key)| (key_id.to_string(), verify_key.key.clone()))
+			.collect(),
+	)]
+	.into();
+
+	verify_json(&public_keys, &before).expect("old signature verifies");
+	verify_json(&public_keys, &after).expect("new signature verifies");
+}
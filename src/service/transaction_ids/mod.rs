@@ -1,26 +1,81 @@
-use std::sync::Arc;
+mod tests;
 
-use conduwuit::{implement, Result};
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use conduwuit::{
+	debug, implement,
+	utils::{self, stream::TryIgnore, MutexMap, MutexMapGuard, ReadyExt},
+	Result, Server,
+};
 use database::{Handle, Map};
-use ruma::{DeviceId, TransactionId, UserId};
+use ruma::{DeviceId, ServerName, TransactionId, UserId};
+use tokio::{
+	sync::Notify,
+	time::{interval, MissedTickBehavior},
+};
 
 pub struct Service {
+	interrupt: Notify,
+	servernametxnid_mutex: MutexMap<String, ()>,
+	services: Services,
 	db: Data,
 }
 
+struct Services {
+	server: Arc<Server>,
+}
+
 struct Data {
+	servernametxnid_response: Arc<Map>,
 	userdevicetxnid_response: Arc<Map>,
 }
 
+/// How long the response to a federation transaction is kept for replays.
+/// Servers retry a transaction well within this, and give up or move on to
+/// new transactions long before it runs out.
+const SERVER_TXNID_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// How often expired federation transaction responses are removed.
+const SERVER_TXNID_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[async_trait]
 impl crate::Service for Service {
 	fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
 		Ok(Arc::new(Self {
+			interrupt: Notify::new(),
+			servernametxnid_mutex: MutexMap::new(),
+			services: Services { server: args.server.clone() },
 			db: Data {
+				servernametxnid_response: args.db["servernametxnid_response"].clone(),
 				userdevicetxnid_response: args.db["userdevicetxnid_response"].clone(),
 			},
 		}))
 	}
 
+	#[tracing::instrument(skip_all, name = "transaction_ids", level = "debug")]
+	async fn worker(self: Arc<Self>) -> Result<()> {
+		let mut i = interval(SERVER_TXNID_PRUNE_INTERVAL);
+		i.set_missed_tick_behavior(MissedTickBehavior::Delay);
+		i.reset_after(SERVER_TXNID_PRUNE_INTERVAL);
+		loop {
+			tokio::select! {
+				() = self.interrupt.notified() => break,
+				_ = i.tick() => (),
+			}
+
+			if !self.services.server.running() {
+				break;
+			}
+
+			self.prune_server_txnids().await;
+		}
+
+		Ok(())
+	}
+
+	fn interrupt(&self) { self.interrupt.notify_waiters(); }
+
 	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 }
 
@@ -32,13 +87,8 @@ pub fn add_txnid(
 	txn_id: &TransactionId,
 	data: &[u8],
 ) {
-	let mut key = user_id.as_bytes().to_vec();
-	key.push(0xFF);
-	key.extend_from_slice(device_id.map(DeviceId::as_bytes).unwrap_or_default());
-	key.push(0xFF);
-	key.extend_from_slice(txn_id.as_bytes());
-
-	self.db.userdevicetxnid_response.insert(&key, data);
+	let key = (user_id, device_id, txn_id);
+	self.db.userdevicetxnid_response.put_raw(key, data);
 }
 
 // If there's no entry, this is a new transaction
@@ -52,3 +102,77 @@ pub async fn existing_txnid(
 	let key = (user_id, device_id, txn_id);
 	self.db.userdevicetxnid_response.qry(&key).await
 }
+
+/// Serializes handling of a federation transaction, so a retry arriving while
+/// the first attempt is still in flight waits for it and replays its response
+/// instead of handling the same PDUs twice.
+#[implement(Service)]
+pub async fn lock_server_txnid(
+	&self,
+	origin: &ServerName,
+	txn_id: &TransactionId,
+) -> MutexMapGuard<String, ()> {
+	// server names cannot contain whitespace, so the key is unambiguous
+	let key = format!("{origin} {txn_id}");
+	self.servernametxnid_mutex.lock(key.as_str()).await
+}
+
+#[implement(Service)]
+pub fn add_server_txnid(&self, origin: &ServerName, txn_id: &TransactionId, data: &[u8]) {
+	let key = (origin, txn_id);
+	let now = utils::millis_since_unix_epoch();
+	self.db
+		.servernametxnid_response
+		.put_raw(key, server_txnid_entry(now, data));
+}
+
+// If there's no entry, this federation transaction has not been handled yet or
+// was handled too long ago to be a retry
+#[implement(Service)]
+pub async fn existing_server_txnid(
+	&self,
+	origin: &ServerName,
+	txn_id: &TransactionId,
+) -> Option<Vec<u8>> {
+	let key = (origin, txn_id);
+	let entry = self.db.servernametxnid_response.qry(&key).await.ok()?;
+	let now = utils::millis_since_unix_epoch();
+
+	unexpired_response(&entry, now).map(ToOwned::to_owned)
+}
+
+#[implement(Service)]
+async fn prune_server_txnids(&self) {
+	let now = utils::millis_since_unix_epoch();
+	let mut pruned: usize = 0;
+	self.db
+		.servernametxnid_response
+		.raw_stream()
+		.ignore_err()
+		.ready_filter(|(_, entry)| unexpired_response(entry, now).is_none())
+		.ready_for_each(|(key, _)| {
+			self.db.servernametxnid_response.remove(key);
+			pruned = pruned.saturating_add(1);
+		})
+		.await;
+
+	if pruned > 0 {
+		debug!("Pruned {pruned} expired federation transaction responses");
+	}
+}
+
+/// A stored transaction response, prefixed with the time it was stored at.
+fn server_txnid_entry(now: u64, response: &[u8]) -> Vec<u8> {
+	let mut entry = Vec::with_capacity(response.len().saturating_add(8));
+	entry.extend_from_slice(&now.to_be_bytes());
+	entry.extend_from_slice(response);
+	entry
+}
+
+/// The response in a stored entry, unless it is older than the TTL.
+fn unexpired_response(entry: &[u8], now: u64) -> Option<&[u8]> {
+	let (stored_at, response) = entry.split_first_chunk::<8>()?;
+	let age = now.saturating_sub(u64::from_be_bytes(*stored_at));
+
+	(u128::from(age) < SERVER_TXNID_TTL.as_millis()).then_some(response)
+}
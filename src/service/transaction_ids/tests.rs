@@ -0,0 +1,41 @@
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+
+use ruma::{owned_event_id, OwnedEventId};
+
+use super::{server_txnid_entry, unexpired_response, SERVER_TXNID_TTL};
+
+type Response = BTreeMap<OwnedEventId, Result<(), String>>;
+
+fn ttl_ms() -> u64 { SERVER_TXNID_TTL.as_millis().try_into().expect("fits") }
+
+#[test]
+fn replayed_txn_returns_original_response() {
+	let response: Response = [
+		(owned_event_id!("$accepted:example.org"), Ok(())),
+		(owned_event_id!("$rejected:example.org"), Err("Event is invalid".to_owned())),
+	]
+	.into();
+
+	let stored = serde_json::to_vec(&response).expect("serializes");
+	let entry = server_txnid_entry(1_000, &stored);
+
+	let replayed = unexpired_response(&entry, 1_000 + 30_000).expect("still replayable");
+	let replayed: Response = serde_json::from_slice(replayed).expect("deserializes");
+
+	assert_eq!(replayed, response);
+}
+
+#[test]
+fn replayed_txn_expires() {
+	let entry = server_txnid_entry(0, b"{}");
+
+	assert!(unexpired_response(&entry, ttl_ms().saturating_sub(1)).is_some());
+	assert!(unexpired_response(&entry, ttl_ms()).is_none());
+}
+
+#[test]
+fn malformed_txn_entry_is_expired() {
+	assert!(unexpired_response(b"{}", 1_000).is_none());
+}
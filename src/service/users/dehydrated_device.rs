@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+
+use conduwuit::{implement, Err, Result};
+use database::{Deserialized, Json};
+use ruma::{
+	api::client::device::Device, encryption::OneTimeKey, serde::Raw, DeviceId,
+	MilliSecondsSinceUnixEpoch, OneTimeKeyAlgorithm, OneTimeKeyName, OwnedDeviceId, OwnedKeyId,
+	UserId,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue as RawJsonValue;
+
+/// A device kept on the server while the user has no running client, so that
+/// to-device messages and one-time-key claims directed at it still succeed.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DehydratedDevice {
+	/// Unique ID of the dehydrated device.
+	pub device_id: OwnedDeviceId,
+
+	/// Opaque device data, encrypted client-side.
+	pub device_data: Box<RawJsonValue>,
+}
+
+/// Stores a dehydrated device for the user. Only one is kept per user; any
+/// previous dehydrated device is removed first. Fails if the device ID belongs
+/// to another of the user's devices.
+#[implement(super::Service)]
+pub async fn set_dehydrated_device(
+	&self,
+	user_id: &UserId,
+	device: DehydratedDevice,
+	one_time_keys: &BTreeMap<OwnedKeyId<OneTimeKeyAlgorithm, OneTimeKeyName>, Raw<OneTimeKey>>,
+) -> Result {
+	let existing = self.get_dehydrated_device(user_id).await.ok();
+	let device_exists = self
+		.get_device_metadata(user_id, &device.device_id)
+		.await
+		.is_ok();
+
+	let dehydrated = existing.as_ref().map(|existing| &*existing.device_id);
+	if let Some(replaced) =
+		replaced_dehydrated_device(&device.device_id, dehydrated, device_exists)?
+	{
+		self.remove_device(user_id, replaced).await;
+	}
+
+	let key = (user_id, &device.device_id);
+	let metadata = Device {
+		device_id: device.device_id.clone(),
+		display_name: None,
+		last_seen_ip: None,
+		last_seen_ts: Some(MilliSecondsSinceUnixEpoch::now()),
+	};
+
+	super::increment(&self.db.userid_devicelistversion, user_id.as_bytes());
+	self.db.userdeviceid_metadata.put(key, Json(metadata));
+	for (one_time_key_key, one_time_key_value) in one_time_keys {
+		self.add_one_time_key(user_id, &device.device_id, one_time_key_key, one_time_key_value)
+			.await?;
+	}
+
+	self.db
+		.userid_dehydrateddevice
+		.raw_put(user_id, Json(&device));
+
+	self.mark_device_key_update(user_id).await;
+
+	Ok(())
+}
+
+/// Returns the user's dehydrated device, if one is stored.
+#[implement(super::Service)]
+pub async fn get_dehydrated_device(&self, user_id: &UserId) -> Result<DehydratedDevice> {
+	self.db
+		.userid_dehydrateddevice
+		.get(user_id)
+		.await
+		.deserialized()
+}
+
+/// Rehydrates the user's dehydrated device: returns it and removes it along
+/// with its device entry and pending to-device events.
+#[implement(super::Service)]
+pub async fn take_dehydrated_device(&self, user_id: &UserId) -> Result<DehydratedDevice> {
+	let device = self.get_dehydrated_device(user_id).await?;
+
+	self.db.userid_dehydrateddevice.remove(user_id);
+	self.remove_device(user_id, &device.device_id).await;
+
+	Ok(device)
+}
+
+/// Checks that `device_id` can be used for a new dehydrated device and returns
+/// the user's current dehydrated device, which the new one replaces.
+pub(super) fn replaced_dehydrated_device<'a>(
+	device_id: &DeviceId,
+	dehydrated: Option<&'a DeviceId>,
+	device_exists: bool,
+) -> Result<Option<&'a DeviceId>> {
+	if device_exists && dehydrated != Some(device_id) {
+		return Err!(Request(InvalidParam("Device ID {device_id} is already in use.")));
+	}
+
+	Ok(dehydrated)
+}
@@ -0,0 +1,166 @@
+use conduwuit::{
+	err, error, implement,
+	utils::{stream::TryIgnore, ReadyExt},
+	Err, Result,
+};
+use database::{Deserialized, Ignore, Interfix, Json};
+use futures::StreamExt;
+use ruma::{
+	encryption::OneTimeKey, serde::Raw, DeviceId, KeyId, OneTimeKeyAlgorithm, OneTimeKeyName,
+	OwnedKeyId, UserId,
+};
+use serde::{Deserialize, Serialize};
+
+/// A key handed out in place of a one-time key once a device has run out of
+/// them. Unlike one-time keys it stays stored after being claimed, and is
+/// only marked as used until the device uploads a replacement.
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct FallbackKey {
+	key_id: OwnedKeyId<OneTimeKeyAlgorithm, OneTimeKeyName>,
+	key: Raw<OneTimeKey>,
+	used: bool,
+}
+
+impl FallbackKey {
+	pub(super) fn new(
+		key_id: &KeyId<OneTimeKeyAlgorithm, OneTimeKeyName>,
+		key: &Raw<OneTimeKey>,
+	) -> Self {
+		Self {
+			key_id: key_id.to_owned(),
+			key: key.clone(),
+			used: false,
+		}
+	}
+
+	/// Marks the key as handed out. Returns false if it already was.
+	pub(super) fn mark_used(&mut self) -> bool { !std::mem::replace(&mut self.used, true) }
+
+	/// The key's algorithm, unless the key has been handed out.
+	pub(super) fn unused_algorithm(&self) -> Option<OneTimeKeyAlgorithm> {
+		(!self.used).then(|| self.key_id.algorithm())
+	}
+}
+
+/// Stores a fallback key for the device, replacing any previous fallback key
+/// of the same algorithm.
+#[implement(super::Service)]
+pub async fn add_fallback_key(
+	&self,
+	user_id: &UserId,
+	device_id: &DeviceId,
+	key_id: &KeyId<OneTimeKeyAlgorithm, OneTimeKeyName>,
+	key: &Raw<OneTimeKey>,
+) -> Result {
+	let userdeviceid = (user_id, device_id);
+	if self
+		.db
+		.userdeviceid_metadata
+		.qry(&userdeviceid)
+		.await
+		.is_err()
+	{
+		return Err!(Database(error!(
+			?user_id,
+			?device_id,
+			"User does not exist or device has no metadata."
+		)));
+	}
+
+	let fallback_key = FallbackKey::new(key_id, key);
+
+	let algorithm = key_id.algorithm();
+	let key = (user_id, device_id, algorithm.as_str());
+	self.db
+		.userdeviceid_fallbackkey
+		.put(key, Json(fallback_key));
+
+	let count = self.services.globals.next_count()?;
+	self.db.userid_lastonetimekeyupdate.raw_put(user_id, count);
+
+	Ok(())
+}
+
+/// Claims a one-time key of the device, handing out its fallback key once
+/// the one-time keys have run out.
+#[implement(super::Service)]
+pub async fn claim_one_time_key(
+	&self,
+	user_id: &UserId,
+	device_id: &DeviceId,
+	algorithm: &OneTimeKeyAlgorithm,
+) -> Result<(OwnedKeyId<OneTimeKeyAlgorithm, OneTimeKeyName>, Raw<OneTimeKey>)> {
+	let one_time_key = self.take_one_time_key(user_id, device_id, algorithm).await;
+
+	if !needs_fallback(&one_time_key) {
+		return one_time_key;
+	}
+
+	self.take_fallback_key(user_id, device_id, algorithm).await
+}
+
+/// Only running out of one-time keys falls back; any other error is passed
+/// through rather than handing out the fallback key early.
+pub(super) fn needs_fallback<T>(one_time_key: &Result<T>) -> bool { one_time_key.is_not_found() }
+
+/// Returns the device's fallback key for the algorithm and marks it as used.
+/// The key itself is kept so it can be handed out again.
+#[implement(super::Service)]
+pub async fn take_fallback_key(
+	&self,
+	user_id: &UserId,
+	device_id: &DeviceId,
+	algorithm: &OneTimeKeyAlgorithm,
+) -> Result<(OwnedKeyId<OneTimeKeyAlgorithm, OneTimeKeyName>, Raw<OneTimeKey>)> {
+	let key = (user_id, device_id, algorithm.as_str());
+	let mut fallback_key: FallbackKey = self
+		.db
+		.userdeviceid_fallbackkey
+		.qry(&key)
+		.await
+		.deserialized()
+		.map_err(|_| err!(Request(NotFound("No fallback key found"))))?;
+
+	if fallback_key.mark_used() {
+		self.db
+			.userdeviceid_fallbackkey
+			.put(key, Json(&fallback_key));
+
+		let count = self.services.globals.next_count()?;
+		self.db.userid_lastonetimekeyupdate.raw_put(user_id, count);
+	}
+
+	Ok((fallback_key.key_id, fallback_key.key))
+}
+
+/// Returns the algorithms for which the device has a fallback key that has
+/// not been handed out yet.
+#[implement(super::Service)]
+pub async fn unused_fallback_key_types(
+	&self,
+	user_id: &UserId,
+	device_id: &DeviceId,
+) -> Vec<OneTimeKeyAlgorithm> {
+	let prefix = (user_id, device_id, Interfix);
+	self.db
+		.userdeviceid_fallbackkey
+		.stream_prefix(&prefix)
+		.ignore_err()
+		.ready_filter_map(|(_, fallback_key): (Ignore, FallbackKey)| {
+			fallback_key.unused_algorithm()
+		})
+		.collect()
+		.await
+}
+
+/// Removes all fallback keys of the device.
+#[implement(super::Service)]
+pub(super) async fn remove_fallback_keys(&self, user_id: &UserId, device_id: &DeviceId) {
+	let prefix = (user_id, device_id, Interfix);
+	self.db
+		.userdeviceid_fallbackkey
+		.keys_prefix_raw(&prefix)
+		.ignore_err()
+		.ready_for_each(|key| self.db.userdeviceid_fallbackkey.remove(key))
+		.await;
+}
@@ -0,0 +1,112 @@
+use conduwuit::{implement, utils, Result};
+use ruma::{
+	events::{
+		push_rules::{PushRulesEvent, PushRulesEventContent},
+		GlobalAccountDataEventType,
+	},
+	push::Ruleset,
+	OwnedMxcUri, OwnedUserId,
+};
+use serde::Deserialize;
+
+/// An account carried over from another homeserver.
+#[derive(Debug, Deserialize)]
+pub struct ImportedUser {
+	pub user_id: OwnedUserId,
+
+	/// Password hash as stored by the previous server. Entries without a
+	/// well-formed hash are rejected; an empty hash would mark the account as
+	/// deactivated.
+	pub password_hash: Option<String>,
+
+	pub displayname: Option<String>,
+
+	pub avatar_url: Option<OwnedMxcUri>,
+}
+
+/// Outcome of [`Service::import`](super::Service::import).
+#[derive(Debug, Default)]
+pub struct ImportReport {
+	/// Accounts which were created.
+	pub created: Vec<OwnedUserId>,
+
+	/// Accounts skipped because the user ID is already registered.
+	pub conflicts: Vec<OwnedUserId>,
+
+	/// Accounts skipped because the user ID is not local to this server.
+	pub rejected: Vec<OwnedUserId>,
+
+	/// Accounts skipped because the user ID is historical or the password
+	/// hash is missing or malformed.
+	pub invalid: Vec<OwnedUserId>,
+}
+
+/// Creates many accounts at once with their existing password hashes and
+/// profiles. Entries which cannot be created are skipped and reported rather
+/// than aborting the import.
+#[implement(super::Service)]
+pub async fn import<I>(&self, entries: I) -> Result<ImportReport>
+where
+	I: IntoIterator<Item = ImportedUser> + Send,
+	I::IntoIter: Send,
+{
+	let _cork = self.services.db.cork_and_flush();
+
+	let mut report = ImportReport::default();
+	for entry in entries {
+		if !self.services.globals.user_is_local(&entry.user_id) {
+			report.rejected.push(entry.user_id);
+			continue;
+		}
+
+		let Some(password_hash) = valid_password_hash(&entry) else {
+			report.invalid.push(entry.user_id);
+			continue;
+		};
+
+		if self.exists(&entry.user_id).await {
+			report.conflicts.push(entry.user_id);
+			continue;
+		}
+
+		self.create(&entry.user_id, None)?;
+		self.db
+			.userid_password
+			.insert(&entry.user_id, password_hash);
+
+		self.set_displayname(&entry.user_id, entry.displayname);
+		self.set_avatar_url(&entry.user_id, entry.avatar_url);
+
+		// Initial account data
+		self.services
+			.account_data
+			.update(
+				None,
+				&entry.user_id,
+				GlobalAccountDataEventType::PushRules.to_string().into(),
+				&serde_json::to_value(PushRulesEvent {
+					content: PushRulesEventContent {
+						global: Ruleset::server_default(&entry.user_id),
+					},
+				})
+				.expect("to json value always works"),
+			)
+			.await?;
+
+		report.created.push(entry.user_id);
+	}
+
+	Ok(report)
+}
+
+/// The entry's password hash, if the entry can be imported at all.
+pub(super) fn valid_password_hash(entry: &ImportedUser) -> Option<&str> {
+	if entry.user_id.is_historical() {
+		return None;
+	}
+
+	entry
+		.password_hash
+		.as_deref()
+		.filter(|hash| utils::hash::check_password_hash(hash).is_ok())
+}
@@ -1,3 +1,8 @@
+mod dehydrated_device;
+mod fallback_key;
+mod import;
+mod tests;
+
 use std::{collections::BTreeMap, mem, mem::size_of, sync::Arc};
 
 use conduwuit::{
@@ -19,6 +24,10 @@ use ruma::{
 };
 use serde_json::json;
 
+pub use self::{
+	dehydrated_device::DehydratedDevice,
+	import::{ImportReport, ImportedUser},
+};
 use crate::{account_data, admin, globals, rooms, Dep};
 
 pub struct Service {
@@ -43,11 +52,13 @@ struct Data {
 	openidtoken_expiresatuserid: Arc<Map>,
 	todeviceid_events: Arc<Map>,
 	token_userdeviceid: Arc<Map>,
+	userdeviceid_fallbackkey: Arc<Map>,
 	userdeviceid_metadata: Arc<Map>,
 	userdeviceid_token: Arc<Map>,
 	userfilterid_filter: Arc<Map>,
 	userid_avatarurl: Arc<Map>,
 	userid_blurhash: Arc<Map>,
+	userid_dehydrateddevice: Arc<Map>,
 	userid_devicelistversion: Arc<Map>,
 	userid_displayname: Arc<Map>,
 	userid_lastonetimekeyupdate: Arc<Map>,
@@ -78,11 +89,13 @@ impl crate::Service for Service {
 				openidtoken_expiresatuserid: args.db["openidtoken_expiresatuserid"].clone(),
 				todeviceid_events: args.db["todeviceid_events"].clone(),
 				token_userdeviceid: args.db["token_userdeviceid"].clone(),
+				userdeviceid_fallbackkey: args.db["userdeviceid_fallbackkey"].clone(),
 				userdeviceid_metadata: args.db["userdeviceid_metadata"].clone(),
 				userdeviceid_token: args.db["userdeviceid_token"].clone(),
 				userfilterid_filter: args.db["userfilterid_filter"].clone(),
 				userid_avatarurl: args.db["userid_avatarurl"].clone(),
 				userid_blurhash: args.db["userid_blurhash"].clone(),
+				userid_dehydrateddevice: args.db["userid_dehydrateddevice"].clone(),
 				userid_devicelistversion: args.db["userid_devicelistversion"].clone(),
 				userid_displayname: args.db["userid_displayname"].clone(),
 				userid_lastonetimekeyupdate: args.db["userid_lastonetimekeyupdate"].clone(),
@@ -124,6 +137,7 @@ impl Service {
 	/// Create a new user account on this homeserver.
 	#[inline]
 	pub fn create(&self, user_id: &UserId, password: Option<&str>) -> Result<()> {
+		self.services.server.check_writable()?;
 		self.set_password(user_id, password)
 	}
 
@@ -284,6 +298,12 @@ impl Service {
 			))));
 		}
 
+		if let Some(max_devices) = self.device_limit_reached(user_id).await {
+			return Err!(Request(Forbidden(
+				"You have reached the maximum of {max_devices} devices, log out of one first."
+			)));
+		}
+
 		let key = (user_id, device_id);
 		let val = Device {
 			device_id: device_id.into(),
@@ -297,6 +317,23 @@ impl Service {
 		self.set_token(user_id, device_id, token).await
 	}
 
+	/// Returns `max_devices_per_user` if the user may not add another device.
+	/// Admins are exempt and the dehydrated device is not counted. Usable
+	/// before the account exists, which counts as holding no devices.
+	pub async fn device_limit_reached(&self, user_id: &UserId) -> Option<usize> {
+		let max_devices = self.services.server.config.max_devices_per_user?;
+		let dehydrated = self.get_dehydrated_device(user_id).await.ok();
+		let dehydrated = dehydrated.as_ref().map(|device| &*device.device_id);
+		let device_count = self
+			.all_device_ids(user_id)
+			.ready_filter(|device_id| counts_toward_limit(device_id, dehydrated))
+			.count()
+			.await;
+
+		(at_device_limit(max_devices, device_count) && !self.is_admin(user_id).await)
+			.then_some(max_devices)
+	}
+
 	/// Removes a device from a user.
 	pub async fn remove_device(&self, user_id: &UserId, device_id: &DeviceId) {
 		let userdeviceid = (user_id, device_id);
@@ -318,6 +355,8 @@ impl Service {
 
 		// TODO: Remove onetimekeys
 
+		self.remove_fallback_keys(user_id, device_id).await;
+
 		increment(&self.db.userid_devicelistversion, user_id.as_bytes());
 
 		self.db.userdeviceid_metadata.del(userdeviceid);
@@ -391,21 +430,14 @@ impl Service {
 			)));
 		}
 
-		let mut key = user_id.as_bytes().to_vec();
-		key.push(0xFF);
-		key.extend_from_slice(device_id.as_bytes());
-		key.push(0xFF);
 		// TODO: Use DeviceKeyId::to_string when it's available (and update everything,
 		// because there are no wrapping quotation marks anymore)
-		key.extend_from_slice(
-			serde_json::to_string(one_time_key_key)
-				.expect("DeviceKeyId::to_string always works")
-				.as_bytes(),
-		);
+		let one_time_key_key = serde_json::to_string(one_time_key_key)?;
 
+		let key = (user_id, device_id, one_time_key_key);
 		self.db
 			.onetimekeyid_onetimekeys
-			.raw_put(key, Json(one_time_key_value));
+			.put(key, Json(one_time_key_value));
 
 		let count = self.services.globals.next_count().unwrap();
 		self.db.userid_lastonetimekeyupdate.raw_put(user_id, count);
@@ -444,29 +476,26 @@ impl Service {
 			.onetimekeyid_onetimekeys
 			.raw_stream_prefix(&prefix)
 			.ignore_err()
-			.map(|(key, val)| {
+			.map(|(key, val)| -> Result<_> {
 				self.db.onetimekeyid_onetimekeys.remove(key);
 
 				let key = key
 					.rsplit(|&b| b == 0xFF)
 					.next()
-					.ok_or_else(|| err!(Database("OneTimeKeyId in db is invalid.")))
-					.unwrap();
+					.ok_or_else(|| err!(Database("OneTimeKeyId in db is invalid.")))?;
 
 				let key = serde_json::from_slice(key)
-					.map_err(|e| err!(Database("OneTimeKeyId in db is invalid. {e}")))
-					.unwrap();
+					.map_err(|e| err!(Database("OneTimeKeyId in db is invalid. {e}")))?;
 
 				let val = serde_json::from_slice(val)
-					.map_err(|e| err!(Database("OneTimeKeys in db are invalid. {e}")))
-					.unwrap();
+					.map_err(|e| err!(Database("OneTimeKeys in db are invalid. {e}")))?;
 
-				(key, val)
+				Ok((key, val))
 			})
 			.next()
 			.await;
 
-		one_time_key.ok_or_else(|| err!(Request(NotFound("No one-time-key found"))))
+		one_time_key.unwrap_or_else(|| Err!(Request(NotFound("No one-time-key found"))))
 	}
 
 	pub async fn count_one_time_keys(
@@ -1072,6 +1101,14 @@ where
 	Ok(cross_signing_key)
 }
 
+pub(super) fn at_device_limit(max_devices: usize, device_count: usize) -> bool {
+	device_count >= max_devices
+}
+
+pub(super) fn counts_toward_limit(device_id: &DeviceId, dehydrated: Option<&DeviceId>) -> bool {
+	dehydrated != Some(device_id)
+}
+
 //TODO: this is an ABA
 fn increment(db: &Arc<Map>, key: &[u8]) {
 	let old = db.get_blocking(key);
@@ -0,0 +1,179 @@
+#![cfg(test)]
+
+use conduwuit::{err, utils, Result};
+use ruma::{
+	device_id, encryption::OneTimeKey, owned_device_id, owned_user_id, serde::Raw, KeyId,
+	OneTimeKeyAlgorithm, OwnedUserId,
+};
+use serde_json::value::to_raw_value;
+
+use super::{
+	at_device_limit, counts_toward_limit,
+	dehydrated_device::replaced_dehydrated_device,
+	fallback_key::{needs_fallback, FallbackKey},
+	import::valid_password_hash,
+	DehydratedDevice, ImportedUser,
+};
+
+fn entry(user_id: OwnedUserId, password_hash: Option<String>) -> ImportedUser {
+	ImportedUser {
+		user_id,
+		password_hash,
+		displayname: None,
+		avatar_url: None,
+	}
+}
+
+#[test]
+fn import_accepts_argon_hash() {
+	let hash = utils::hash::password("temp123").expect("digest");
+	let entry = entry(owned_user_id!("@alice:example.com"), Some(hash.clone()));
+
+	assert_eq!(valid_password_hash(&entry), Some(hash.as_str()));
+}
+
+#[test]
+fn import_rejects_missing_hash() {
+	let entry = entry(owned_user_id!("@alice:example.com"), None);
+
+	assert_eq!(valid_password_hash(&entry), None);
+}
+
+#[test]
+fn import_rejects_malformed_hash() {
+	for hash in ["", "temp123", "$2b$12$notargon"] {
+		let entry = entry(owned_user_id!("@alice:example.com"), Some(hash.to_owned()));
+
+		assert_eq!(valid_password_hash(&entry), None, "{hash:?} was accepted");
+	}
+}
+
+#[test]
+fn import_rejects_other_algorithms() {
+	let hash = "$pbkdf2-sha256$i=600000$c29tZXNhbHQ$ZXhhbXBsZWhhc2hleGFtcGxlaGFzaGV4YW1wbGU";
+	let entry = entry(owned_user_id!("@alice:example.com"), Some(hash.to_owned()));
+
+	assert_eq!(valid_password_hash(&entry), None);
+}
+
+#[test]
+fn import_rejects_historical_user_id() {
+	let hash = utils::hash::password("temp123").expect("digest");
+	let entry = entry(owned_user_id!("@Alice:example.com"), Some(hash));
+
+	assert_eq!(valid_password_hash(&entry), None);
+}
+
+#[test]
+fn device_limit() {
+	assert!(!at_device_limit(2, 0));
+	assert!(!at_device_limit(2, 1));
+	assert!(at_device_limit(2, 2));
+	assert!(at_device_limit(2, 3));
+}
+
+#[test]
+fn device_limit_zero_refuses_first_device() {
+	// checked at registration before the account is created
+	assert!(at_device_limit(0, 0));
+}
+
+#[test]
+fn dehydrated_device_not_counted() {
+	let dehydrated = Some(device_id!("DEHYDRATED"));
+
+	assert!(!counts_toward_limit(device_id!("DEHYDRATED"), dehydrated));
+	assert!(counts_toward_limit(device_id!("PHONE"), dehydrated));
+	assert!(counts_toward_limit(device_id!("PHONE"), None));
+}
+
+#[test]
+fn dehydrated_device_round_trip() {
+	let device = DehydratedDevice {
+		device_id: owned_device_id!("DEHYDRATED"),
+		device_data: to_raw_value(&serde_json::json!({
+			"algorithm": "m.dehydration.v1.olm",
+			"device_pickle": "encrypted",
+		}))
+		.expect("valid json"),
+	};
+
+	let stored = serde_json::to_vec(&device).expect("serializes");
+	let loaded: DehydratedDevice = serde_json::from_slice(&stored).expect("deserializes");
+
+	assert_eq!(loaded.device_id, device.device_id);
+	assert_eq!(loaded.device_data.get(), device.device_data.get());
+}
+
+#[test]
+fn dehydrated_device_upload_replace_and_claim() {
+	let first = device_id!("DEHYDRATED1");
+	let second = device_id!("DEHYDRATED2");
+
+	// upload: nothing to replace
+	let replaced = replaced_dehydrated_device(first, None, false).expect("new device id");
+	assert_eq!(replaced, None);
+
+	// replace with a new device; the first one is removed
+	let replaced = replaced_dehydrated_device(second, Some(first), false).expect("new device id");
+	assert_eq!(replaced, Some(first));
+
+	// re-upload under the same device id
+	let replaced = replaced_dehydrated_device(second, Some(second), true).expect("own device id");
+	assert_eq!(replaced, Some(second));
+
+	// claimed: the client now runs as a real device under that id
+	replaced_dehydrated_device(second, None, true)
+		.expect_err("device id of the rehydrated device was reused");
+}
+
+#[test]
+fn dehydrated_device_cannot_take_over_device() {
+	let dehydrated = Some(device_id!("DEHYDRATED"));
+
+	let result = replaced_dehydrated_device(device_id!("PHONE"), dehydrated, true);
+	assert!(result.is_err(), "an existing device would be overwritten");
+
+	let result = replaced_dehydrated_device(device_id!("PHONE"), None, true);
+	assert!(result.is_err(), "an existing device would be overwritten");
+}
+
+fn fallback_key() -> FallbackKey {
+	let key_id = KeyId::parse("signed_curve25519:AAAAHQ").expect("valid key id");
+	let key: Raw<OneTimeKey> = Raw::from_json(
+		to_raw_value(&serde_json::json!({
+			"key": "zKbLg+NrIjpnagy+pIY6uPL4ZwEG2v+8F9lmgsnlZzs",
+			"fallback": true,
+			"signatures": {},
+		}))
+		.expect("valid json"),
+	);
+
+	FallbackKey::new(&key_id, &key)
+}
+
+#[test]
+fn fallback_only_once_one_time_keys_run_out() {
+	let exhausted: Result<()> = Err(err!(Request(NotFound("No one-time-key found"))));
+	let failed: Result<()> = Err(err!(Database("I/O error")));
+
+	assert!(needs_fallback(&exhausted));
+	assert!(!needs_fallback(&failed));
+	assert!(!needs_fallback(&Ok(())));
+}
+
+#[test]
+fn fallback_key_marked_used_but_kept() {
+	let mut key = fallback_key();
+	assert_eq!(key.unused_algorithm(), Some(OneTimeKeyAlgorithm::SignedCurve25519));
+
+	// claimed once the one-time keys are exhausted
+	assert!(key.mark_used());
+	assert_eq!(key.unused_algorithm(), None);
+
+	// still stored and handed out again, without another update
+	let stored = serde_json::to_vec(&key).expect("serializes");
+	let mut key: FallbackKey = serde_json::from_slice(&stored).expect("deserializes");
+	assert!(!key.mark_used());
+	assert_eq!(key.unused_algorithm(), None);
+}